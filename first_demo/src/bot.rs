@@ -0,0 +1,395 @@
+// --auto and --simulate: let a bot play.
+//
+//     first_demo --auto=binary                  # watch a bot play one game
+//     first_demo --auto=human --simulate=1000   # 1000 games, then statistics
+//
+// Strategies:
+//
+// binary  guess the middle of what is left; never needs more than log2(range) + 1 guesses
+// random  guess anything that is left
+// human   aim for the middle, but miss by up to a quarter of what is left, like people do
+//
+// The bot guesses through Game::guess, the same comparison the human loop uses, and keeps
+// track of the numbers that are still possible. An answer that leaves no number possible
+// means the game compared wrongly, so every simulation doubles as a check that the
+// Ordering feedback is consistent.
+
+use crate::config::Config;
+use crate::game::{Feedback, Game, State};
+use crate::locale::Catalogue;
+use rand::distributions::Uniform;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    Binary,
+    Random,
+    Human,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Result<Strategy, String> {
+        match name {
+            "binary" => Ok(Strategy::Binary),
+            "random" => Ok(Strategy::Random),
+            "human" => Ok(Strategy::Human),
+            _ => Err(format!(
+                "Unknown strategy {}, use binary, random or human",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Binary => "binary",
+            Strategy::Random => "random",
+            Strategy::Human => "human",
+        }
+    }
+}
+
+pub struct Bot {
+    strategy: Strategy,
+    // every number from low to high, both included, may still be the secret
+    low: u32,
+    high: u32,
+    rng: StdRng,
+}
+
+impl Bot {
+    pub fn new(strategy: Strategy, config: &Config, seed: u64) -> Bot {
+        Bot {
+            strategy,
+            low: config.min,
+            high: config.max,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn next_guess(&mut self) -> u32 {
+        let (low, high) = (self.low, self.high);
+        // low + (high - low) / 2 rather than (low + high) / 2, which overflows near u32::MAX
+        let middle = low + (high - low) / 2;
+
+        match self.strategy {
+            Strategy::Binary => middle,
+            Strategy::Random => self.rng.sample(Uniform::new_inclusive(low, high)),
+            // middle is halfway, so missing it by a quarter stays between low and high
+            Strategy::Human => {
+                let miss = (high - low) / 4;
+                self.rng
+                    .sample(Uniform::new_inclusive(middle - miss, middle + miss))
+            }
+        }
+    }
+
+    // Narrows down the possible numbers, or says why the feedback can't be right
+    pub fn learn(&mut self, guess: u32, feedback: Feedback) -> Result<(), String> {
+        let contradiction = |what: &str| {
+            Err(format!(
+                "{} is {}, but the number has to be between {} and {}",
+                guess, what, self.low, self.high
+            ))
+        };
+
+        match feedback {
+            Feedback::TooSmall if guess >= self.high => contradiction("too small"),
+            Feedback::TooBig if guess <= self.low => contradiction("too big"),
+            Feedback::Correct if guess < self.low || guess > self.high => contradiction("correct"),
+            Feedback::OutOfRange => contradiction("out of range"),
+            // the bot only guesses numbers still possible, and a guess made before isn't
+            Feedback::Repeated => Err(format!("{} was guessed before", guess)),
+            Feedback::TooSmall => {
+                self.low = self.low.max(guess + 1);
+                Ok(())
+            }
+            Feedback::TooBig => {
+                self.high = self.high.min(guess - 1);
+                Ok(())
+            }
+            Feedback::Correct => {
+                self.low = guess;
+                self.high = guess;
+                Ok(())
+            }
+        }
+    }
+}
+
+// Plays game to the end, calling each with every guess and what the game said to it
+pub fn play<F: FnMut(u32, Feedback)>(
+    bot: &mut Bot,
+    game: &mut Game,
+    mut each: F,
+) -> Result<State, String> {
+    while game.state() == State::Playing {
+        let guess = bot.next_guess();
+        let feedback = game.guess(guess);
+        each(guess, feedback);
+        bot.learn(guess, feedback)?;
+    }
+
+    if game.state() == State::Won && bot.low != game.secret() {
+        return Err(format!(
+            "won by guessing {}, but the number was {}",
+            bot.low,
+            game.secret()
+        ));
+    }
+    Ok(game.state())
+}
+
+// The bot's seed for a game, kept apart from the game's own so the two don't draw the same numbers
+fn bot_seed(seed: u64) -> u64 {
+    seed ^ 0x9e37_79b9_7f4a_7c15
+}
+
+// One game, printed the way the human loop prints it
+pub fn run_one<W: Write>(
+    strategy: Strategy,
+    config: &Config,
+    seed: u64,
+    out: &mut W,
+) -> Result<State, String> {
+    let messages = Catalogue::new(config.language.unwrap_or_default());
+    let mut game = Game::new(config.clone(), seed);
+    let mut bot = Bot::new(strategy, config, bot_seed(seed));
+    let mut written = Ok(());
+
+    let state = play(&mut bot, &mut game, |guess, feedback| {
+        let said = match feedback {
+            Feedback::TooSmall => "number.too-small",
+            Feedback::TooBig => "number.too-big",
+            Feedback::Correct => "number.win",
+            Feedback::OutOfRange => "bot.out-of-range",
+            Feedback::Repeated => "bot.repeated",
+        };
+        if written.is_ok() {
+            written = writeln!(
+                out,
+                "{}\n{}",
+                messages.text("bot.guessed", &[("guess", &guess)]),
+                messages.text(said, &[])
+            );
+        }
+    })?;
+    written.map_err(|e| e.to_string())?;
+
+    if state == State::Lost {
+        let lost = messages.text("number.lost", &[("secret", &game.secret())]);
+        writeln!(out, "{}", lost).map_err(|e| e.to_string())?;
+    }
+    Ok(state)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub games: u32,
+    pub lost: u32,
+    // how many games were won with 1, 2, 3... guesses, at index 0, 1, 2...
+    pub won_after: Vec<u32>,
+}
+
+impl Stats {
+    pub fn won(&self) -> u32 {
+        self.won_after.iter().sum()
+    }
+
+    fn add(&mut self, state: State, attempts: u32) {
+        self.games += 1;
+        if state == State::Lost {
+            self.lost += 1;
+            return;
+        }
+
+        let index = attempts as usize - 1;
+        if self.won_after.len() <= index {
+            self.won_after.resize(index + 1, 0);
+        }
+        self.won_after[index] += 1;
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let won = self.won();
+        if won == 0 {
+            return None;
+        }
+
+        let total: u64 = self
+            .won_after
+            .iter()
+            .enumerate()
+            .map(|(index, &count)| (index as u64 + 1) * count as u64)
+            .sum();
+        Some(total as f64 / won as f64)
+    }
+
+    // The smallest number of guesses that half of the won games needed at most
+    pub fn median(&self) -> Option<u32> {
+        let won = self.won();
+        if won == 0 {
+            return None;
+        }
+
+        let mut seen = 0;
+        for (index, &count) in self.won_after.iter().enumerate() {
+            seen += count;
+            if seen >= won.div_ceil(2) {
+                return Some(index as u32 + 1);
+            }
+        }
+        None
+    }
+
+    pub fn write<W: Write>(&self, messages: &Catalogue, out: &mut W) -> io::Result<()> {
+        let won = self.won();
+        writeln!(
+            out,
+            "{}",
+            messages.plural("bot.won", self.games as u64, &[("won", &won)])
+        )?;
+
+        if let (Some(mean), Some(median)) = (self.mean(), self.median()) {
+            let fewest = self.won_after.iter().position(|&count| count > 0).unwrap() + 1;
+            let mean = format!("{:.2}", mean);
+            let most = self.won_after.len();
+            writeln!(
+                out,
+                "{}",
+                messages.text(
+                    "bot.guesses",
+                    &[
+                        ("fewest", &fewest),
+                        ("mean", &mean),
+                        ("median", &median),
+                        ("most", &most)
+                    ]
+                )
+            )?;
+
+            for (index, &count) in self.won_after.iter().enumerate() {
+                writeln!(out, "{:>5} {:>8}", index + 1, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Plays games games, the first with seed and each next one with the seed after
+pub fn simulate(
+    strategy: Strategy,
+    config: &Config,
+    seed: u64,
+    games: u32,
+) -> Result<Stats, String> {
+    let mut stats = Stats::default();
+
+    for game_seed in (0..games as u64).map(|index| seed.wrapping_add(index)) {
+        let mut game = Game::new(config.clone(), game_seed);
+        let mut bot = Bot::new(strategy, config, bot_seed(game_seed));
+
+        let state = play(&mut bot, &mut game, |_, _| {})
+            .map_err(|e| format!("game with --seed={}: {}", game_seed, e))?;
+        stats.add(state, game.attempts());
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Language;
+
+    fn config(args: &[&str]) -> Config {
+        Config::new(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    #[test]
+    fn binary_search_always_wins_hard() {
+        // hard leaves exactly the 10 guesses that halving 1000 numbers needs
+        let stats = simulate(Strategy::Binary, &config(&["--difficulty=hard"]), 0, 500).unwrap();
+
+        assert_eq!(500, stats.won());
+        assert_eq!(10, stats.won_after.len());
+    }
+
+    #[test]
+    fn every_strategy_gets_consistent_feedback() {
+        let wide = config(&["--min=0", "--max=4294967295", "--max-attempts=1000000"]);
+
+        for strategy in [Strategy::Binary, Strategy::Random, Strategy::Human] {
+            for config in [config(&["--difficulty=easy"]), wide.clone()] {
+                let stats = simulate(strategy, &config, 7, 200).unwrap();
+                assert_eq!(200, stats.won(), "{}", strategy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn contradictions_are_caught() {
+        let mut bot = Bot::new(Strategy::Binary, &config(&["--min=1", "--max=10"]), 0);
+
+        bot.learn(5, Feedback::TooSmall).unwrap();
+        assert!(bot.learn(3, Feedback::TooBig).is_err());
+        assert!(bot.learn(4, Feedback::Correct).is_err());
+        assert!(bot.learn(10, Feedback::TooSmall).is_err());
+        assert!(bot.learn(7, Feedback::OutOfRange).is_err());
+    }
+
+    #[test]
+    fn statistics() {
+        let mut stats = Stats::default();
+        for &attempts in &[3, 1, 3, 4] {
+            stats.add(State::Won, attempts);
+        }
+        stats.add(State::Lost, 10);
+
+        assert_eq!(vec![1, 0, 2, 1], stats.won_after);
+        assert_eq!(Some(2.75), stats.mean());
+        assert_eq!(Some(3), stats.median());
+
+        let mut out = Vec::new();
+        stats.write(&Catalogue::default(), &mut out).unwrap();
+        assert_eq!(
+            "\
+won 4 of 5 games
+guesses: fewest 1, mean 2.75, median 3, most 4
+    1        1
+    2        0
+    3        2
+    4        1
+",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        stats
+            .write(&Catalogue::new(Language::German), &mut out)
+            .unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("4 von 5 Spielen gewonnen\nVersuche: wenigste 1, Mittel 2.75, Median 3"));
+    }
+
+    #[test]
+    fn one_game_transcript() {
+        let mut out = Vec::new();
+        let state = run_one(
+            Strategy::Binary,
+            &config(&["--min=1", "--max=3"]),
+            5,
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(State::Won, state);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("The bot guessed: 2\n"), "{}", out);
+        assert!(out.ends_with("You win!\n"), "{}", out);
+    }
+}
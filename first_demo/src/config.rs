@@ -0,0 +1,545 @@
+// Command line options for the guessing game.
+//
+//     first_demo                                    # normal: 1 to 100, 10 guesses
+//     first_demo --difficulty=easy                  # 1 to 50, as many guesses as it takes
+//     first_demo --difficulty=hard --max-attempts=12
+//     first_demo --min=1 --max=1000000
+//     first_demo --seed=42                          # the same secret number every time
+//     first_demo --name=alice                       # the name on the leaderboard
+//     first_demo --warmer-colder                    # closer than the last guess or not
+//     first_demo scores                             # show the leaderboard
+//     first_demo --auto=human --simulate=1000       # let a bot play, see bot.rs
+//     first_demo words --daily                      # guess a word instead, see words.rs
+//     first_demo mastermind --pegs=5 --colours=8    # crack a code, see mastermind.rs
+//     first_demo serve --port=7878 --difficulty=hard  # host rounds on the network,
+//     first_demo join 192.168.1.20:7878 --name=bob    # and play them, see multiplayer.rs
+//     first_demo --record session.log               # keep the session,
+//     first_demo replay session.log                 # and check it plays the same, see record.rs
+//     first_demo --lang=de                          # in German, see locale.rs
+//
+// A preset picks the range and the number of guesses, and --min, --max and
+// --max-attempts change single values on top of it, wherever they are on the line.
+
+use crate::bot::Strategy;
+use crate::locale::Language;
+use crate::mastermind::{DEFAULT_COLOURS, DEFAULT_PEGS, MAX_COLOURS, MAX_PEGS};
+use std::env;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Result<Difficulty, String> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!(
+                "Unknown difficulty {}, use easy, normal or hard",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Hard leaves exactly the guesses halving the range needs, so only careful play wins
+    fn preset(self) -> Config {
+        let (max, max_attempts) = match self {
+            Difficulty::Easy => (50, None),
+            Difficulty::Normal => (100, Some(10)),
+            Difficulty::Hard => (1000, Some(10)),
+        };
+
+        Config {
+            min: 1,
+            max,
+            max_attempts,
+            seed: None,
+            player: None,
+            command: Command::Play,
+            auto: None,
+            simulate: None,
+            warmer_colder: false,
+            daily: false,
+            record: None,
+            language: None,
+        }
+    }
+}
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    // Play in the terminal
+    Play,
+    // Show the leaderboard
+    Scores,
+    // Guess a word instead of a number
+    Words,
+    // Crack a code of pegs in colours
+    Mastermind { pegs: usize, colours: u8 },
+    // Host rounds for players on the network, on this port
+    Serve(u16),
+    // Play the rounds of the server at this address
+    Join(String),
+    // Play the session recorded in this file again
+    Replay(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    // The secret number is between min and max, both included
+    pub min: u32,
+    pub max: u32,
+    // None: guess until it's right
+    pub max_attempts: Option<u32>,
+    // None: a different game every run
+    pub seed: Option<u64>,
+    // None: the login name
+    pub player: Option<String>,
+    pub command: Command,
+    // Let a bot play with this strategy
+    pub auto: Option<Strategy>,
+    // Play this many games with the bot and print statistics
+    pub simulate: Option<u32>,
+    // Say whether each guess came closer than the one before
+    pub warmer_colder: bool,
+    // words: today's word rather than a random one
+    pub daily: bool,
+    // Record the session to this file
+    pub record: Option<String>,
+    // None: English here; main asks the environment for it
+    pub language: Option<Language>,
+}
+
+fn number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} needs a whole number, got {}", option, value))
+}
+
+impl Config {
+    pub fn new(args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let mut difficulty = Difficulty::Normal;
+        let mut min = None;
+        let mut max = None;
+        let mut max_attempts = None;
+        let mut seed = None;
+        let mut player = None;
+        let mut command = Command::Play;
+        let mut port = None;
+        let mut pegs = None;
+        let mut colours = None;
+        let mut auto = None;
+        let mut simulate = None;
+        let mut warmer_colder = false;
+        let mut daily = false;
+        let mut record = None;
+        let mut language = None;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let commands = ["scores", "words", "mastermind", "serve", "join", "replay"];
+            if commands.contains(&arg.as_str()) && command != Command::Play {
+                return Err(String::from(
+                    "Use only one of scores, words, mastermind, serve, join and replay",
+                ));
+            }
+
+            if arg == "scores" {
+                command = Command::Scores;
+                continue;
+            }
+            if arg == "words" {
+                command = Command::Words;
+                continue;
+            }
+            if arg == "mastermind" {
+                // pegs and colours are filled in below, like the port
+                command = Command::Mastermind {
+                    pegs: DEFAULT_PEGS,
+                    colours: DEFAULT_COLOURS,
+                };
+                continue;
+            }
+            if arg == "serve" {
+                // the port is filled in below, it may come later on the line
+                command = Command::Serve(DEFAULT_PORT);
+                continue;
+            }
+            if arg == "join" {
+                match args.next() {
+                    Some(address) => command = Command::Join(address),
+                    None => {
+                        return Err(String::from(
+                            "join needs an address, like 192.168.1.20:7878",
+                        ))
+                    }
+                }
+                continue;
+            }
+            if arg == "replay" {
+                match args.next() {
+                    Some(path) => command = Command::Replay(path),
+                    None => return Err(String::from("replay needs a session file")),
+                }
+                continue;
+            }
+            // --record session.log as well as --record=session.log
+            if arg == "--record" {
+                match args.next() {
+                    Some(path) => record = Some(path),
+                    None => return Err(String::from("--record needs a file")),
+                }
+                continue;
+            }
+            if arg == "--daily" {
+                daily = true;
+                continue;
+            }
+            if arg == "--warmer-colder" {
+                warmer_colder = true;
+                continue;
+            }
+            if arg == "--auto" {
+                auto = Some(Strategy::Binary);
+                continue;
+            }
+
+            let (option, value) = match arg.find('=') {
+                Some(at) => (&arg[..at], &arg[at + 1..]),
+                None => return Err(format!("Unknown argument {}", arg)),
+            };
+
+            match option {
+                "--difficulty" => difficulty = Difficulty::parse(value)?,
+                "--min" => min = Some(number(option, value)?),
+                "--max" => max = Some(number(option, value)?),
+                "--max-attempts" => max_attempts = Some(number(option, value)?),
+                "--seed" => seed = Some(number(option, value)?),
+                "--name" if value.trim().is_empty() => {
+                    return Err(String::from("--name needs a name"))
+                }
+                "--name" => player = Some(value.trim().to_string()),
+                "--auto" => auto = Some(Strategy::parse(value)?),
+                "--simulate" => simulate = Some(number(option, value)?),
+                "--port" => port = Some(number(option, value)?),
+                "--pegs" => pegs = Some(number(option, value)?),
+                "--colours" | "--colors" => colours = Some(number(option, value)?),
+                "--record" if value.is_empty() => {
+                    return Err(String::from("--record needs a file"))
+                }
+                "--record" => record = Some(value.to_string()),
+                "--lang" => language = Some(Language::parse(value)?),
+                _ => return Err(format!("Unknown option {}", option)),
+            }
+        }
+
+        let mut config = difficulty.preset();
+        config.min = min.unwrap_or(config.min);
+        config.max = max.unwrap_or(config.max);
+        if max_attempts.is_some() {
+            config.max_attempts = max_attempts;
+        }
+        config.seed = seed;
+        config.player = player;
+        config.command = match (command, port) {
+            (Command::Serve(_), Some(port)) => Command::Serve(port),
+            (_, Some(_)) => return Err(String::from("--port only goes with serve")),
+            (command, None) => command,
+        };
+        config.command = match (config.command, pegs, colours) {
+            (Command::Mastermind { .. }, Some(pegs), _) if !(1..=MAX_PEGS).contains(&pegs) => {
+                return Err(format!("--pegs goes from 1 to {}", MAX_PEGS))
+            }
+            (Command::Mastermind { .. }, _, Some(colours))
+                if !(2..=MAX_COLOURS).contains(&colours) =>
+            {
+                return Err(format!("--colours goes from 2 to {}", MAX_COLOURS))
+            }
+            (
+                Command::Mastermind {
+                    pegs: p,
+                    colours: c,
+                },
+                pegs,
+                colours,
+            ) => Command::Mastermind {
+                pegs: pegs.unwrap_or(p),
+                colours: colours.unwrap_or(c),
+            },
+            (command, None, None) => command,
+            _ => return Err(String::from("--pegs and --colours only go with mastermind")),
+        };
+        // the statistics are about the number game's strategies
+        if simulate.is_some() && config.command != Command::Play {
+            return Err(String::from("--simulate only goes with the number game"));
+        }
+        // --simulate on its own shows how well the best strategy does
+        config.auto = auto.or_else(|| simulate.map(|_| Strategy::Binary));
+        config.simulate = simulate;
+        config.warmer_colder = warmer_colder;
+        if daily && config.command != Command::Words {
+            return Err(String::from("--daily only goes with words"));
+        }
+        config.daily = daily;
+        // replay takes everything from the session, and the number game's bots play
+        // without input to record
+        match config.command {
+            Command::Play if config.auto.is_some() && record.is_some() => {
+                return Err(String::from("--record only goes with games played by hand"))
+            }
+            Command::Play | Command::Words | Command::Mastermind { .. } => {}
+            _ if record.is_some() => {
+                return Err(String::from(
+                    "--record only goes with playing, not scores, serve, join or replay",
+                ))
+            }
+            _ => {}
+        }
+        config.record = record;
+        config.language = language;
+
+        if config.min > config.max {
+            return Err(format!(
+                "--min ({}) is above --max ({}), there is no number in between",
+                config.min, config.max
+            ));
+        }
+        if config.min == config.max {
+            return Err(format!(
+                "--min and --max are both {}, there is nothing to guess",
+                config.min
+            ));
+        }
+        if config.max_attempts == Some(0) {
+            return Err(String::from("--max-attempts must be at least 1"));
+        }
+        if config.simulate == Some(0) {
+            return Err(String::from("--simulate needs at least 1 game"));
+        }
+
+        Ok(config)
+    }
+
+    // --name, or else the login name
+    pub fn player_name(&self) -> String {
+        self.player
+            .clone()
+            .or_else(|| env::var("USER").ok())
+            .or_else(|| env::var("USERNAME").ok())
+            .unwrap_or_else(|| String::from("player"))
+    }
+
+    // The preset this game is played at, or None if options changed it
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard]
+            .iter()
+            .copied()
+            .find(|difficulty| {
+                let preset = difficulty.preset();
+                (preset.min, preset.max, preset.max_attempts)
+                    == (self.min, self.max, self.max_attempts)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        Config::new(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(Ok(Difficulty::Normal.preset()), parse(&[]));
+        assert_eq!(
+            Ok(Config {
+                min: 1,
+                max: 50,
+                max_attempts: None,
+                seed: None,
+                player: None,
+                command: Command::Play,
+                auto: None,
+                simulate: None,
+                warmer_colder: false,
+                daily: false,
+                record: None,
+                language: None
+            }),
+            parse(&["--difficulty=easy"])
+        );
+    }
+
+    #[test]
+    fn options_change_the_preset() {
+        assert_eq!(
+            Ok(Config {
+                min: 1,
+                max: 1000,
+                max_attempts: Some(12),
+                seed: Some(42),
+                player: None,
+                command: Command::Play,
+                auto: None,
+                simulate: None,
+                warmer_colder: false,
+                daily: false,
+                record: None,
+                language: None
+            }),
+            parse(&["--max-attempts=12", "--seed=42", "--difficulty=hard"])
+        );
+        assert_eq!(
+            Ok(Config {
+                min: 10,
+                max: 20,
+                max_attempts: Some(10),
+                seed: None,
+                player: None,
+                command: Command::Play,
+                auto: None,
+                simulate: None,
+                warmer_colder: false,
+                daily: false,
+                record: None,
+                language: None
+            }),
+            parse(&["--min=10", "--max=20"])
+        );
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        assert!(parse(&["--min=20", "--max=10"]).is_err());
+        assert!(parse(&["--min=5", "--max=5"]).is_err());
+        assert!(parse(&["--min=-1"]).is_err());
+        assert!(parse(&["--max-attempts=0"]).is_err());
+        assert!(parse(&["--seed=lucky"]).is_err());
+        assert!(parse(&["--difficulty=impossible"]).is_err());
+        assert!(parse(&["hard"]).is_err());
+        assert!(parse(&["--name="]).is_err());
+    }
+
+    #[test]
+    fn difficulty_of_a_game() {
+        let hard = parse(&["--difficulty=hard", "--name=alice"]).unwrap();
+        assert_eq!(Some(Difficulty::Hard), hard.difficulty());
+        assert_eq!(Some(String::from("alice")), hard.player);
+
+        let custom = parse(&["--difficulty=hard", "--max-attempts=20"]).unwrap();
+        assert_eq!(None, custom.difficulty());
+
+        // the same numbers as a preset count as that preset
+        let normal = parse(&["--difficulty=easy", "--max=100", "--max-attempts=10"]).unwrap();
+        assert_eq!(Some(Difficulty::Normal), normal.difficulty());
+
+        assert_eq!(Command::Scores, parse(&["scores"]).unwrap().command);
+    }
+
+    #[test]
+    fn bot_options() {
+        assert_eq!(Some(Strategy::Binary), parse(&["--auto"]).unwrap().auto);
+        assert_eq!(
+            Some(Strategy::Human),
+            parse(&["--auto=human"]).unwrap().auto
+        );
+
+        let simulation = parse(&["--simulate=100"]).unwrap();
+        assert_eq!(Some(Strategy::Binary), simulation.auto);
+        assert_eq!(Some(100), simulation.simulate);
+
+        assert!(parse(&["--auto=psychic"]).is_err());
+        assert!(parse(&["--simulate=0"]).is_err());
+        assert_eq!(
+            Err(String::from("--simulate only goes with the number game")),
+            parse(&["mastermind", "--simulate=3"]).map(|config| config.simulate)
+        );
+        assert!(parse(&["words", "--simulate=3"]).is_err());
+        // mastermind has a solver of its own to watch
+        assert!(parse(&["mastermind", "--auto"]).is_ok());
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            Command::Serve(DEFAULT_PORT),
+            parse(&["serve"]).unwrap().command
+        );
+        assert_eq!(
+            Command::Serve(9000),
+            parse(&["--port=9000", "serve"]).unwrap().command
+        );
+        assert_eq!(
+            Command::Join(String::from("localhost:9000")),
+            parse(&["join", "localhost:9000", "--name=bob"])
+                .unwrap()
+                .command
+        );
+
+        assert!(parse(&["join"]).is_err());
+        assert!(parse(&["serve", "scores"]).is_err());
+        assert!(parse(&["words", "--daily"]).unwrap().daily);
+        assert!(parse(&["--daily"]).is_err());
+
+        assert_eq!(
+            Command::Mastermind {
+                pegs: 5,
+                colours: 6
+            },
+            parse(&["mastermind", "--pegs=5"]).unwrap().command
+        );
+        assert!(parse(&["mastermind", "--colours=10"]).is_err());
+        assert!(parse(&["mastermind", "--pegs=0"]).is_err());
+        assert!(parse(&["--pegs=5"]).is_err());
+        assert!(parse(&["--port=9000"]).is_err());
+        assert!(parse(&["serve", "--port=99999"]).is_err());
+    }
+
+    #[test]
+    fn recording() {
+        let config = parse(&["--record", "session.log", "--difficulty=hard"]).unwrap();
+        assert_eq!(Some(String::from("session.log")), config.record);
+        assert_eq!(
+            Some(String::from("words.log")),
+            parse(&["words", "--record=words.log"]).unwrap().record
+        );
+        assert_eq!(
+            Command::Replay(String::from("session.log")),
+            parse(&["replay", "session.log"]).unwrap().command
+        );
+
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["replay"]).is_err());
+        assert!(parse(&["scores", "--record=x.log"]).is_err());
+        assert!(parse(&["replay", "a.log", "--record=b.log"]).is_err());
+        assert!(parse(&["--auto", "--record=x.log"]).is_err());
+        assert!(parse(&["mastermind", "--auto", "--record=x.log"]).is_ok());
+    }
+
+    #[test]
+    fn languages() {
+        assert_eq!(None, parse(&[]).unwrap().language);
+        assert_eq!(
+            Some(Language::German),
+            parse(&["words", "--lang=de"]).unwrap().language
+        );
+        assert!(parse(&["--lang=fr"]).is_err());
+    }
+}
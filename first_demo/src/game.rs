@@ -0,0 +1,559 @@
+// The guessing game itself, apart from the terminal.
+//
+// A Game is a small state machine: it starts Playing, and every guess either leaves it
+// there or ends it as Won or Lost (or Quit, when the player gives up). The secret number
+// comes from a seeded RNG, so the same --seed gives the same game, and play talks through
+// any BufRead and Write, so a test can script a whole session and read back everything
+// that was printed.
+//
+// Besides numbers, the player can type
+//
+// quit     give up and see the number
+// hint     learn which quarter of the numbers still possible has the secret; costs a guess
+// history  list the guesses so far
+//
+// Input that isn't a guess is explained rather than skipped, and guesses out of range or
+// made before don't use up a guess. With --warmer-colder every guess after the first also
+// says whether it came closer to the number than the one before. What it says comes
+// from the catalogue of --lang.
+
+use crate::config::Config;
+use crate::locale::Catalogue;
+use rand::distributions::Uniform;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
+use std::num::IntErrorKind;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Playing,
+    Won,
+    Lost,
+    Quit,
+}
+
+// What a single guess told the player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feedback {
+    // outside min..=max; doesn't use up a guess
+    OutOfRange,
+    // guessed before; doesn't use up a guess either
+    Repeated,
+    TooSmall,
+    TooBig,
+    Correct,
+}
+
+// How a guess compares to the one before it, for --warmer-colder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warmth {
+    Warmer,
+    Colder,
+    Same,
+}
+
+pub struct Game {
+    config: Config,
+    secret: u32,
+    attempts: u32,
+    state: State,
+    // the guesses that counted, in order
+    history: Vec<(u32, Feedback)>,
+    // every number from low to high, both included, may still be the secret as far as
+    // the player has been told
+    low: u32,
+    high: u32,
+    messages: Catalogue,
+}
+
+// A guess typed by the player, or why it isn't one
+pub fn parse_guess(text: &str, messages: &Catalogue) -> Result<u32, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(messages.text("number.empty", &[]));
+    }
+
+    text.parse()
+        .map_err(|e: std::num::ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow => messages.text("number.far-too-big", &[("text", &text)]),
+            _ if text.starts_with('-') && text[1..].parse::<u32>().is_ok() => {
+                messages.text("number.negative", &[])
+            }
+            _ if text.parse::<f64>().is_ok() => {
+                messages.text("number.not-whole", &[("text", &text)])
+            }
+            _ => messages.text("number.not-a-number", &[("text", &format!("{:?}", text))]),
+        })
+}
+
+impl Game {
+    pub fn new(config: Config, seed: u64) -> Game {
+        // new_inclusive rather than gen_range(min, max + 1), which overflows for --max=4294967295
+        let secret =
+            StdRng::seed_from_u64(seed).sample(Uniform::new_inclusive(config.min, config.max));
+
+        Game {
+            secret,
+            attempts: 0,
+            state: State::Playing,
+            history: Vec::new(),
+            low: config.min,
+            high: config.max,
+            messages: Catalogue::new(config.language.unwrap_or_default()),
+            config,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn secret(&self) -> u32 {
+        self.secret
+    }
+
+    // Guesses made so far, including hints, not counting the free ones
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // None when there is no limit
+    pub fn attempts_left(&self) -> Option<u32> {
+        self.config
+            .max_attempts
+            .map(|max_attempts| max_attempts - self.attempts)
+    }
+
+    pub fn history(&self) -> &[(u32, Feedback)] {
+        &self.history
+    }
+
+    // Uses up a guess, and ends the game if it was the last one
+    fn attempt(&mut self) {
+        self.attempts += 1;
+        if self.attempts_left() == Some(0) {
+            self.state = State::Lost;
+        }
+    }
+
+    pub fn guess(&mut self, guess: u32) -> Feedback {
+        assert_eq!(State::Playing, self.state, "the game is over");
+
+        if guess < self.config.min || guess > self.config.max {
+            return Feedback::OutOfRange;
+        }
+        if self.history.iter().any(|&(before, _)| before == guess) {
+            return Feedback::Repeated;
+        }
+
+        let feedback = match guess.cmp(&self.secret) {
+            Ordering::Less => Feedback::TooSmall,
+            Ordering::Greater => Feedback::TooBig,
+            Ordering::Equal => Feedback::Correct,
+        };
+        match feedback {
+            Feedback::TooSmall => self.low = self.low.max(guess + 1),
+            Feedback::TooBig => self.high = self.high.min(guess - 1),
+            _ => {}
+        }
+        self.history.push((guess, feedback));
+
+        self.attempt();
+        if feedback == Feedback::Correct {
+            self.state = State::Won;
+        }
+        feedback
+    }
+
+    // The quarter of the numbers still possible that has the secret in it, as (low, high).
+    // Costs a guess, and losing the game if it was the last one.
+    pub fn hint(&mut self) -> (u32, u32) {
+        assert_eq!(State::Playing, self.state, "the game is over");
+
+        let width = (self.high - self.low) / 4 + 1;
+        let low = self.low + (self.secret - self.low) / width * width;
+        let high = low.saturating_add(width - 1).min(self.high);
+        self.low = low;
+        self.high = high;
+
+        self.attempt();
+        (low, high)
+    }
+
+    pub fn quit(&mut self) {
+        self.state = State::Quit;
+    }
+
+    // Whether the last guess came closer to the secret than the one before it
+    pub fn warmth(&self) -> Option<Warmth> {
+        let (last, previous) = match self.history.as_slice() {
+            [.., (previous, _), (last, _)] => (*last, *previous),
+            _ => return None,
+        };
+
+        let distance = |guess: u32| guess.abs_diff(self.secret);
+        Some(match distance(last).cmp(&distance(previous)) {
+            Ordering::Less => Warmth::Warmer,
+            Ordering::Greater => Warmth::Colder,
+            Ordering::Equal => Warmth::Same,
+        })
+    }
+
+    fn write_history<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if self.history.is_empty() {
+            return writeln!(out, "{}", self.messages.text("number.no-history", &[]));
+        }
+
+        for (index, (guess, feedback)) in self.history.iter().enumerate() {
+            let said = match feedback {
+                Feedback::TooSmall => "number.history.too-small",
+                Feedback::TooBig => "number.history.too-big",
+                _ => "number.history.right",
+            };
+            writeln!(
+                out,
+                "{:>3}. {:<10} {}",
+                index + 1,
+                guess,
+                self.messages.text(said, &[])
+            )?;
+        }
+        Ok(())
+    }
+
+    // Plays until the game is over or input runs out, and returns the state it ended in
+    pub fn play<R: BufRead, W: Write>(&mut self, mut input: R, out: &mut W) -> io::Result<State> {
+        let messages = self.messages;
+        let secret = self.secret;
+        let telling_secret = |key: &str| messages.text(key, &[("secret", &secret)]);
+
+        writeln!(out, "{}", messages.text("number.intro", &[]))?;
+        writeln!(
+            out,
+            "{}",
+            messages.text(
+                "number.range",
+                &[("min", &self.config.min), ("max", &self.config.max)]
+            )
+        )?;
+        if let Some(max_attempts) = self.config.max_attempts {
+            writeln!(
+                out,
+                "{}",
+                messages.plural("guesses", max_attempts.into(), &[])
+            )?;
+        }
+        writeln!(out, "{}", messages.text("number.commands", &[]))?;
+
+        while self.state == State::Playing {
+            writeln!(out, "{}", messages.text("prompt", &[]))?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out, "{}", telling_secret("number.bye"))?;
+                break;
+            }
+
+            match line.trim() {
+                "quit" => {
+                    self.quit();
+                    writeln!(out, "{}", telling_secret("number.bye"))?;
+                    break;
+                }
+                "history" => {
+                    self.write_history(out)?;
+                    continue;
+                }
+                "hint" => {
+                    let (low, high) = self.hint();
+                    let hint = messages.text("number.hint", &[("low", &low), ("high", &high)]);
+                    writeln!(out, "{}", hint)?;
+                }
+                text => {
+                    let guess = match parse_guess(text, &messages) {
+                        Ok(guess) => guess,
+                        Err(why) => {
+                            writeln!(out, "{}", why)?;
+                            continue;
+                        }
+                    };
+                    self.write_feedback(guess, out)?;
+                }
+            }
+
+            if self.state == State::Lost {
+                writeln!(out, "{}", telling_secret("number.lost"))?;
+            }
+        }
+
+        Ok(self.state)
+    }
+
+    fn write_feedback<W: Write>(&mut self, guess: u32, out: &mut W) -> io::Result<()> {
+        let messages = self.messages;
+        let said = |key: &str| messages.text(key, &[]);
+
+        match self.guess(guess) {
+            Feedback::OutOfRange => writeln!(
+                out,
+                "{}",
+                messages.text(
+                    "number.out-of-range",
+                    &[
+                        ("guess", &guess),
+                        ("min", &self.config.min),
+                        ("max", &self.config.max)
+                    ]
+                )
+            ),
+            Feedback::Repeated => writeln!(
+                out,
+                "{}",
+                messages.text("number.repeated", &[("guess", &guess)])
+            ),
+            feedback => {
+                writeln!(
+                    out,
+                    "{}",
+                    messages.text("number.guessed", &[("guess", &guess)])
+                )?;
+                match feedback {
+                    Feedback::TooSmall => writeln!(out, "{}", said("number.too-small"))?,
+                    Feedback::TooBig => writeln!(out, "{}", said("number.too-big"))?,
+                    _ => return writeln!(out, "{}", said("number.win")),
+                }
+
+                match self.warmth().filter(|_| self.config.warmer_colder) {
+                    Some(Warmth::Warmer) => writeln!(out, "{}", said("number.warmer")),
+                    Some(Warmth::Colder) => writeln!(out, "{}", said("number.colder")),
+                    Some(Warmth::Same) => writeln!(out, "{}", said("number.same")),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Command;
+
+    fn config(max_attempts: Option<u32>) -> Config {
+        Config {
+            min: 1,
+            max: 100,
+            max_attempts,
+            seed: None,
+            player: None,
+            command: Command::Play,
+            auto: None,
+            simulate: None,
+            warmer_colder: false,
+            daily: false,
+            record: None,
+            language: None,
+        }
+    }
+
+    // Plays a scripted session, returning the state it ended in and everything printed
+    fn session(game: &mut Game, input: &str) -> (State, String) {
+        let mut out = Vec::new();
+        let state = game.play(input.as_bytes(), &mut out).unwrap();
+        (state, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn same_seed_same_secret() {
+        assert_eq!(
+            Game::new(config(None), 7).secret(),
+            Game::new(config(None), 7).secret()
+        );
+
+        let secrets: Vec<u32> = (0..20)
+            .map(|seed| Game::new(config(None), seed).secret())
+            .collect();
+        assert!(secrets.iter().all(|secret| (1..=100).contains(secret)));
+        assert!(secrets.iter().any(|&secret| secret != secrets[0]));
+    }
+
+    #[test]
+    fn winning_session() {
+        let mut game = Game::new(config(Some(10)), 42);
+        let secret = game.secret();
+        let input = format!(
+            "{}\nabc\n\n500\n-3\n2.5\n99999999999\n{}\n{}\nhistory\n{}\n",
+            secret - 1,
+            secret - 1,
+            secret + 1,
+            secret
+        );
+        let (state, out) = session(&mut game, &input);
+
+        assert_eq!(State::Won, state);
+        assert_eq!(3, game.attempts());
+        assert_eq!(
+            format!(
+                "\
+Guess the number!
+It is between 1 and 100.
+You have 10 guesses.
+Type quit, hint or history at any time.
+Please input your guess.
+You guessed: {low}
+Too small!
+Please input your guess.
+\"abc\" is not a number.
+Please input your guess.
+Type a number, or quit, hint or history.
+Please input your guess.
+500 is out of range, the number is between 1 and 100.
+Please input your guess.
+The number is never negative.
+Please input your guess.
+2.5 is not a whole number.
+Please input your guess.
+99999999999 is far too big to be the number.
+Please input your guess.
+You already guessed {low}; this one doesn't count.
+Please input your guess.
+You guessed: {high}
+Too big!
+Please input your guess.
+  1. {low:<10} too small
+  2. {high:<10} too big
+Please input your guess.
+You guessed: {secret}
+You win!
+",
+                low = secret - 1,
+                high = secret + 1,
+                secret = secret
+            ),
+            out
+        );
+    }
+
+    #[test]
+    fn losing_session() {
+        let mut game = Game::new(config(Some(2)), 42);
+        let secret = game.secret();
+        let wrong: Vec<u32> = (1..=3).filter(|&guess| guess != secret).collect();
+        let input = format!("{}\nten\n{}\n", wrong[0], wrong[1]);
+        let (state, out) = session(&mut game, &input);
+
+        assert_eq!(State::Lost, state);
+        assert_eq!(Some(0), game.attempts_left());
+        assert!(out.ends_with(&format!(
+            "Out of guesses! The number was {}.\n",
+            game.secret()
+        )));
+    }
+
+    #[test]
+    fn session_in_german() {
+        let config = Config {
+            language: Some(crate::locale::Language::German),
+            ..config(Some(1))
+        };
+        let mut game = Game::new(config, 42);
+        let secret = game.secret();
+        let wrong = if secret == 1 { 2 } else { 1 };
+        let (state, out) = session(&mut game, &format!("x\n{}\n", wrong));
+
+        assert_eq!(State::Lost, state);
+        assert_eq!(
+            format!(
+                "\
+Rate die Zahl!
+Sie liegt zwischen 1 und 100.
+Du hast 1 Versuch.
+Mit quit, hint oder history jederzeit aufgeben, einen Hinweis oder die Tipps bekommen.
+Bitte gib deinen Tipp ein.
+\"x\" ist keine Zahl.
+Bitte gib deinen Tipp ein.
+Dein Tipp: {wrong}
+Zu {size}!
+Keine Versuche mehr! Die Zahl war {secret}.
+",
+                wrong = wrong,
+                size = if wrong < secret { "klein" } else { "groß" },
+                secret = secret
+            ),
+            out
+        );
+    }
+
+    #[test]
+    fn quit_and_hints() {
+        let mut game = Game::new(config(Some(2)), 42);
+        let secret = game.secret();
+        let (state, out) = session(&mut game, "hint\nquit\nnever read\n");
+
+        assert_eq!(State::Quit, state);
+        assert_eq!(1, game.attempts());
+        assert!(out.ends_with(&format!("Bye! The number was {}.\n", secret)));
+
+        // every hint costs a guess, and narrows down to a quarter of what was left
+        let mut game = Game::new(config(Some(2)), 42);
+        let (low, high) = game.hint();
+        assert!(
+            low <= secret && secret <= high && high - low < 25,
+            "{}-{}",
+            low,
+            high
+        );
+        assert_eq!(State::Playing, game.state());
+        game.hint();
+        assert_eq!(State::Lost, game.state());
+    }
+
+    #[test]
+    fn warmer_and_colder() {
+        let mut config = config(None);
+        config.warmer_colder = true;
+        let mut game = Game::new(config, 42);
+        let secret = game.secret();
+        let far = if secret > 50 { 1 } else { 100 };
+        let near = if secret > 50 { secret - 1 } else { secret + 1 };
+
+        game.guess(far);
+        assert_eq!(None, game.warmth());
+        game.guess(near);
+        assert_eq!(Some(Warmth::Warmer), game.warmth());
+        game.guess(2 * secret - near);
+        assert_eq!(Some(Warmth::Same), game.warmth());
+
+        let mut game = Game::new(game.config.clone(), 42);
+        let (_, out) = session(&mut game, &format!("{}\n{}\n", near, far));
+        assert!(out.contains("Too big!\nColder.\n") || out.contains("Too small!\nColder.\n"));
+    }
+
+    #[test]
+    fn input_runs_out() {
+        let mut game = Game::new(config(None), 42);
+        let (state, out) = session(&mut game, "");
+
+        assert_eq!(State::Playing, state);
+        assert!(out.ends_with(&format!("Bye! The number was {}.\n", game.secret())));
+    }
+
+    #[test]
+    fn out_of_range_guesses_are_free() {
+        let mut game = Game::new(config(Some(1)), 42);
+
+        assert_eq!(Feedback::OutOfRange, game.guess(0));
+        assert_eq!(Feedback::OutOfRange, game.guess(101));
+        assert_eq!(Some(1), game.attempts_left());
+        assert_eq!(State::Playing, game.state());
+
+        let mut game = Game::new(config(Some(2)), 42);
+        let wrong = if game.secret() == 1 { 2 } else { 1 };
+        game.guess(wrong);
+        assert_eq!(Feedback::Repeated, game.guess(wrong));
+        assert_eq!(Some(1), game.attempts_left());
+        assert_eq!(State::Playing, game.state());
+    }
+}
@@ -0,0 +1,61 @@
+// The guessing game: options in config, the game itself in game, finished games in
+// scores, the computer player in bot and games over the network in multiplayer.
+// words and mastermind are the other games, guessing a word or a code, record
+// keeps sessions to play them again and locale has what they say in other
+// languages. main only wires them to the terminal.
+
+pub mod bot;
+pub mod config;
+pub mod game;
+pub mod locale;
+pub mod mastermind;
+pub mod multiplayer;
+pub mod record;
+pub mod scores;
+pub mod words;
+
+pub use bot::Strategy;
+pub use config::{Command, Config, Difficulty};
+pub use game::{Feedback, Game, State};
+pub use locale::{Catalogue, Language};
+pub use mastermind::{Mastermind, Solver};
+pub use record::{Recording, Session};
+pub use scores::Score;
+pub use words::{Evaluation, Mark, WordGame};
+
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+// Plays the game config asks for with seed, reading guesses from input. Everything a
+// game does comes from config, seed and input, which is what lets replay play a
+// recorded session again. The number game comes back for the leaderboard.
+pub fn play<R: BufRead, W: Write>(
+    config: &Config,
+    seed: u64,
+    input: R,
+    out: &mut W,
+) -> Result<Option<Game>, Box<dyn Error>> {
+    match config.command {
+        Command::Play => {
+            let mut game = Game::new(config.clone(), seed);
+            game.play(input, out)?;
+            Ok(Some(game))
+        }
+        // for --daily the seed is already the day's
+        Command::Words => {
+            let mut game = WordGame::random(seed).in_language(config.language.unwrap_or_default());
+            game.play(input, out)?;
+            Ok(None)
+        }
+        Command::Mastermind { pegs, colours } => {
+            let mut game = Mastermind::new(config, pegs, colours, seed);
+            if config.auto.is_some() {
+                game.solve(out)?;
+            } else {
+                game.play(input, out)?;
+            }
+            Ok(None)
+        }
+        _ => Err(format!("{:?} isn't a game to play", config.command).into()),
+    }
+}
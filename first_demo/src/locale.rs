@@ -0,0 +1,362 @@
+// What first_demo says, in English, German or Chinese.
+//
+//     first_demo --lang=de                 # auf Deutsch
+//     LANG=zh_CN.UTF-8 first_demo words    # 中文
+//
+// Without --lang the language comes from LC_ALL, LC_MESSAGES or LANG, the first one set,
+// and anything but de or zh there is English. The catalogues are locales/*.txt, built into
+// the program, with a line for every message:
+//
+//     number.range = It is between {min} and {max}.
+//
+// The text runs to the end of the line, spaces included, and {name} is filled in by the
+// game. A message that depends on a count has a line for each plural form the language
+// has, like guesses.one and guesses.other; see Language::plural. A message a catalogue
+// doesn't have is taken from English, so a half-done translation still works, and the
+// tests check that none of the shipped ones is half done.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Chinese,
+}
+
+pub const LANGUAGES: [Language; 3] = [Language::English, Language::German, Language::Chinese];
+
+impl Language {
+    pub fn parse(code: &str) -> Result<Language, String> {
+        LANGUAGES
+            .iter()
+            .copied()
+            .find(|language| language.code() == code)
+            .ok_or_else(|| format!("Unknown language {}, use en, de or zh", code))
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::Chinese => "zh",
+        }
+    }
+
+    // A locale like de_DE.UTF-8, or None for one there is no catalogue for
+    pub fn from_locale(locale: &str) -> Option<Language> {
+        let code = locale.split(['_', '.', '@']).next().unwrap_or("");
+        Language::parse(&code.to_lowercase()).ok()
+    }
+
+    // What the environment asks for, like gettext reads it
+    pub fn from_env() -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Language::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    // The plural forms the catalogue has for a message about count things
+    pub fn forms(self) -> &'static [&'static str] {
+        match self {
+            Language::English | Language::German => &["one", "other"],
+            // Chinese doesn't change words for how many there are
+            Language::Chinese => &["other"],
+        }
+    }
+
+    // Which of the forms goes with count
+    pub fn plural(self, count: u64) -> &'static str {
+        match self {
+            Language::English | Language::German if count == 1 => "one",
+            _ => "other",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Language::English => include_str!("locales/en.txt"),
+            Language::German => include_str!("locales/de.txt"),
+            Language::Chinese => include_str!("locales/zh.txt"),
+        }
+    }
+}
+
+type Messages = HashMap<&'static str, &'static str>;
+
+// The key = text lines of a catalogue, skipping blank lines and # comments
+fn parse(source: &'static str) -> Result<Messages, String> {
+    let mut messages = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, text) = line
+            .split_once(" = ")
+            .ok_or_else(|| format!("line {}: no \" = \" in {:?}", index + 1, line))?;
+        if messages.insert(key.trim(), text).is_some() {
+            return Err(format!("line {}: {} again", index + 1, key.trim()));
+        }
+    }
+
+    Ok(messages)
+}
+
+fn messages(language: Language) -> &'static Messages {
+    static ENGLISH: OnceLock<Messages> = OnceLock::new();
+    static GERMAN: OnceLock<Messages> = OnceLock::new();
+    static CHINESE: OnceLock<Messages> = OnceLock::new();
+
+    let cell = match language {
+        Language::English => &ENGLISH,
+        Language::German => &GERMAN,
+        Language::Chinese => &CHINESE,
+    };
+    // the tests parse every catalogue, so a broken one never ships
+    cell.get_or_init(|| {
+        parse(language.source())
+            .unwrap_or_else(|e| panic!("locales/{}.txt: {}", language.code(), e))
+    })
+}
+
+// The messages of one language, with English for what it is missing
+#[derive(Debug, Clone, Copy)]
+pub struct Catalogue {
+    language: Language,
+    messages: &'static Messages,
+}
+
+impl Default for Catalogue {
+    fn default() -> Catalogue {
+        Catalogue::new(Language::English)
+    }
+}
+
+impl Catalogue {
+    pub fn new(language: Language) -> Catalogue {
+        Catalogue {
+            language,
+            messages: messages(language),
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    // The message for key with every {name} of args filled in
+    pub fn text(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let template = self
+            .messages
+            .get(key)
+            .or_else(|| messages(Language::English).get(key))
+            .copied()
+            // a key nobody has shows up as itself rather than as nothing
+            .unwrap_or(key);
+        fill(template, args)
+    }
+
+    // The message for key about count things, which is {count} in it
+    pub fn plural(&self, key: &str, count: u64, args: &[(&str, &dyn Display)]) -> String {
+        let mut args = args.to_vec();
+        args.push(("count", &count));
+
+        let form = format!("{}.{}", key, self.language.plural(count));
+        match self.messages.get(form.as_str()) {
+            Some(template) => fill(template, &args),
+            // English has its own plural forms
+            None => Catalogue::new(Language::English).text(
+                &format!("{}.{}", key, Language::English.plural(count)),
+                &args,
+            ),
+        }
+    }
+}
+
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // The {names} in a message
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    // A message key without its plural form, and the form
+    fn split_form(key: &str) -> (&str, Option<&str>) {
+        match key.rsplit_once('.') {
+            Some((base, form)) if form == "one" || form == "other" => (base, Some(form)),
+            _ => (key, None),
+        }
+    }
+
+    #[test]
+    fn every_locale_has_every_key() {
+        let english = messages(Language::English);
+
+        // Every language is checked against English below, so English has to have the
+        // messages from outside the games too: the leaderboard, --simulate, serve and
+        // join, and replay
+        for key in [
+            "scores.none",
+            "scores.easy",
+            "scores.guesses.other",
+            "bot.simulation.other",
+            "bot.won.other",
+            "bot.guesses",
+            "multiplayer.serving",
+            "multiplayer.round",
+            "multiplayer.joined",
+            "multiplayer.left",
+            "multiplayer.hint",
+            "multiplayer.won.other",
+            "multiplayer.reveal",
+            "multiplayer.error.name-first",
+            "multiplayer.error.unknown-command",
+            "multiplayer.error.not-a-number",
+            "multiplayer.error.out-of-guesses",
+            "multiplayer.error.range",
+            "multiplayer.error.repeated",
+            "replay.matches.other",
+        ] {
+            assert!(english.contains_key(key), "en is missing {}", key);
+        }
+
+        for language in LANGUAGES {
+            let messages = parse(language.source()).unwrap();
+            let mut missing = Vec::new();
+
+            // what English has, in the forms this language has
+            let mut wanted = BTreeSet::new();
+            for key in english.keys() {
+                match split_form(key) {
+                    (base, Some(_)) => {
+                        for form in language.forms() {
+                            wanted.insert(format!("{}.{}", base, form));
+                        }
+                    }
+                    (key, None) => {
+                        wanted.insert(key.to_string());
+                    }
+                }
+            }
+
+            for key in &wanted {
+                match messages.get(key.as_str()) {
+                    None => missing.push(key.clone()),
+                    Some(text) => {
+                        // the English message with the same names, for plurals the other form
+                        let (base, form) = split_form(key);
+                        let model = form.map_or(key.clone(), |_| format!("{}.other", base));
+                        assert_eq!(
+                            placeholders(english[model.as_str()]),
+                            placeholders(text),
+                            "{} in {}",
+                            key,
+                            language.code()
+                        );
+                    }
+                }
+            }
+            assert!(
+                missing.is_empty(),
+                "{} is missing {:?}",
+                language.code(),
+                missing
+            );
+
+            let extra: Vec<_> = messages
+                .keys()
+                .filter(|key| !wanted.contains(**key))
+                .collect();
+            assert!(
+                extra.is_empty(),
+                "{} has keys English doesn't: {:?}",
+                language.code(),
+                extra
+            );
+        }
+    }
+
+    #[test]
+    fn plural_forms() {
+        let english = Catalogue::new(Language::English);
+        assert_eq!("You have 1 guess.", english.plural("guesses", 1, &[]));
+        assert_eq!("You have 0 guesses.", english.plural("guesses", 0, &[]));
+        assert_eq!("You have 10 guesses.", english.plural("guesses", 10, &[]));
+
+        let german = Catalogue::new(Language::German);
+        assert_eq!("Du hast 1 Versuch.", german.plural("guesses", 1, &[]));
+        assert_eq!("Du hast 3 Versuche.", german.plural("guesses", 3, &[]));
+
+        let chinese = Catalogue::new(Language::Chinese);
+        assert_eq!(
+            chinese.plural("guesses", 1, &[]).replace('1', "2"),
+            chinese.plural("guesses", 2, &[])
+        );
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_english() {
+        let partial = Catalogue {
+            language: Language::German,
+            messages: Box::leak(Box::new(parse("number.win = Gewonnen!\n").unwrap())),
+        };
+
+        assert_eq!("Gewonnen!", partial.text("number.win", &[]));
+        assert_eq!(
+            "It is between 1 and 9.",
+            partial.text("number.range", &[("min", &1), ("max", &9)])
+        );
+        assert_eq!("You have 1 guess.", partial.plural("guesses", 1, &[]));
+        assert_eq!("no.such.key", partial.text("no.such.key", &[]));
+    }
+
+    #[test]
+    fn languages_from_the_environment() {
+        assert_eq!(Some(Language::German), Language::from_locale("de_DE.UTF-8"));
+        assert_eq!(Some(Language::Chinese), Language::from_locale("zh_CN"));
+        assert_eq!(
+            Some(Language::English),
+            Language::from_locale("en_GB.UTF-8")
+        );
+        assert_eq!(None, Language::from_locale("fr_FR.UTF-8"));
+        assert_eq!(None, Language::from_locale("C"));
+
+        assert_eq!(Ok(Language::German), Language::parse("de"));
+        assert!(Language::parse("klingon").is_err());
+    }
+
+    #[test]
+    fn bad_catalogues() {
+        assert!(parse("number.win Gewonnen!\n").is_err());
+        assert!(parse("a = 1\na = 2\n").is_err());
+        // the text keeps its spaces, prompts end in one
+        assert_eq!(
+            Some(&"Guess: "),
+            parse("# prompt\n\nprompt = Guess: \n")
+                .unwrap()
+                .get("prompt")
+        );
+    }
+}
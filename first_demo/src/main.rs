@@ -1,35 +1,122 @@
-use rand::Rng;
-use std::cmp::Ordering;
-use std::io;
+use first_demo::multiplayer::{self, Server};
+use first_demo::{
+    bot, record, scores, words, Catalogue, Command, Config, Language, Recording, Score, Session,
+};
+use std::env;
+use std::error::Error;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
 
 fn main() {
-    println!("Guess the number!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = Config::new(args.iter().cloned()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {}", err);
+        process::exit(1);
+    });
 
-    let secret_number = rand::thread_rng().gen_range(1, 101);
+    if let Err(e) = run(config, &args) {
+        eprintln!("Application error: {}", e);
+        process::exit(1);
+    }
+}
 
-    loop {
-        println!("Please input your guess.");
+fn run(mut config: Config, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = scores::default_path();
+    let language = *config.language.get_or_insert_with(Language::from_env);
+    let messages = Catalogue::new(language);
 
-        let mut guess = String::new();
+    let seed = if config.daily {
+        words::daily_seed(chrono::Local::now().date_naive())
+    } else {
+        config.seed.unwrap_or_else(rand::random)
+    };
 
-        io::stdin()
-            .read_line(&mut guess)
-            .expect("Failed to read line");
+    match &config.command {
+        Command::Play | Command::Words | Command::Mastermind { .. } => {}
+        Command::Scores => {
+            let path = path.ok_or("There is no data directory to keep scores in")?;
+            return Ok(scores::write_leaderboard(
+                &scores::load(&path)?,
+                &messages,
+                &mut io::stdout(),
+            )?);
+        }
+        Command::Replay(session) => {
+            let lines = Session::load(Path::new(session))?
+                .check()
+                .map_err(|e| format!("{}: {}", session, e))?;
+            println!(
+                "{}",
+                messages.plural("replay.matches", lines as u64, &[("session", session)])
+            );
+            return Ok(());
+        }
+        Command::Serve(port) => {
+            let server = Server::bind(("0.0.0.0", *port), config.clone(), seed)?;
+            println!(
+                "{}",
+                messages.text("multiplayer.serving", &[("port", port)])
+            );
+            return Ok(server.run()?);
+        }
+        Command::Join(address) => {
+            let input = BufReader::new(io::stdin());
+            return Ok(multiplayer::join(
+                address,
+                &config.player_name(),
+                &messages,
+                input,
+                &mut io::stdout(),
+            )?);
+        }
+    }
 
-        let guess: u32 = match guess.trim().parse() {
-            Ok(num) => num,
-            Err(_) => continue,
-        };
+    // Bots don't make the leaderboard
+    if let (Command::Play, Some(strategy)) = (&config.command, config.auto) {
+        match config.simulate {
+            Some(games) => {
+                println!(
+                    "{}",
+                    messages.plural(
+                        "bot.simulation",
+                        games as u64,
+                        &[("strategy", &strategy.name()), ("seed", &seed)]
+                    )
+                );
+                bot::simulate(strategy, &config, seed, games)?
+                    .write(&messages, &mut io::stdout())?;
+            }
+            None => {
+                bot::run_one(strategy, &config, seed, &mut io::stdout())?;
+            }
+        }
+        return Ok(());
+    }
 
-        println!("You guessed: {}", guess);
+    let started = Instant::now();
+    let stdin = io::stdin();
+    let game = match &config.record {
+        Some(log) => {
+            let recording =
+                Recording::create(Path::new(log), seed, &record::session_args(args, language))
+                    .map_err(|e| format!("Could not record to {}: {}", log, e))?;
+            let input = recording.input(stdin.lock());
+            let game = first_demo::play(&config, seed, input, &mut recording.output(io::stdout()))?;
+            recording.finish()?;
+            game
+        }
+        None => first_demo::play(&config, seed, stdin.lock(), &mut io::stdout())?,
+    };
 
-        match guess.cmp(&secret_number) {
-            Ordering::Less => println!("Too small!"),
-            Ordering::Greater => println!("Too big!"),
-            Ordering::Equal => {
-                println!("You win!");
-                break;
-            }
+    // A lost score is a shame, but the game itself went fine
+    let score = game.and_then(|game| Score::of(&config, &game, started.elapsed()));
+    if let (Some(path), Some(score)) = (path, score) {
+        if let Err(e) = scores::append(&path, &score) {
+            eprintln!("Could not save the score to {}: {}", path.display(), e);
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,537 @@
+// mastermind: crack a code of coloured pegs.
+//
+//     first_demo mastermind                          # 4 pegs, 6 colours
+//     first_demo mastermind --pegs=5 --colours=8
+//     first_demo mastermind --auto                   # watch the solver crack it
+//
+// Colours are the digits 1 to --colours, so a code is typed like 1342. Every guess is
+// answered with black pegs, for the right colour in the right place, and white pegs, for
+// a right colour in a wrong place. Typing suggest asks the solver for a next guess.
+//
+// The solver is Knuth's: of every possible code, it guesses the one whose worst answer
+// leaves the fewest codes still possible, preferring codes that could be the secret, then
+// the lowest. With 4 pegs and 6 colours it never needs more than 5 guesses. Its first
+// guess is always half one colour and half another (1122), which is where Knuth starts and
+// saves looking at every code when they all still fit.
+//
+// Like the number game it is a state machine over BufRead and Write, speaking --lang; only
+// what a guess is and how it is compared are different.
+
+use crate::config::Config;
+use crate::game::State;
+use crate::locale::Catalogue;
+use rand::distributions::Uniform;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub const DEFAULT_PEGS: usize = 4;
+pub const DEFAULT_COLOURS: u8 = 6;
+pub const MAX_PEGS: usize = 8;
+pub const MAX_COLOURS: u8 = 9;
+
+// The solver looks at every code for every guess, so it stops at this many
+pub const SOLVER_LIMIT: usize = 10_000;
+
+// Colours are 0 to colours - 1 inside, shown one higher
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Code(pub Vec<u8>);
+
+impl Code {
+    pub fn parse(
+        text: &str,
+        pegs: usize,
+        colours: u8,
+        messages: &Catalogue,
+    ) -> Result<Code, String> {
+        let text = text.trim();
+        let mut code = Vec::new();
+
+        for c in text.chars() {
+            match c.to_digit(10) {
+                Some(digit) if (1..=colours as u32).contains(&digit) => code.push(digit as u8 - 1),
+                _ => {
+                    let text = format!("{:?}", c);
+                    return Err(messages.text(
+                        "mastermind.not-a-colour",
+                        &[("text", &text), ("colours", &colours)],
+                    ));
+                }
+            }
+        }
+        if code.len() != pegs {
+            return Err(messages.text(
+                "mastermind.length",
+                &[("pegs", &pegs), ("given", &code.len())],
+            ));
+        }
+
+        Ok(Code(code))
+    }
+
+    // Every code, in order: 1111, 1112... for 4 pegs
+    pub fn all(pegs: usize, colours: u8) -> Vec<Code> {
+        let count = (colours as usize).pow(pegs as u32);
+
+        (0..count)
+            .map(|mut index| {
+                let mut code = vec![0; pegs];
+                for peg in code.iter_mut().rev() {
+                    *peg = (index % colours as usize) as u8;
+                    index /= colours as usize;
+                }
+                Code(code)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for peg in &self.0 {
+            write!(f, "{}", peg + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Feedback {
+    pub black: usize,
+    pub white: usize,
+}
+
+impl Feedback {
+    pub fn of(guess: &Code, secret: &Code) -> Feedback {
+        let black = guess
+            .0
+            .iter()
+            .zip(secret.0.iter())
+            .filter(|(a, b)| a == b)
+            .count();
+
+        // every colour both have, as often as the one with fewer has it, counts once
+        let mut in_guess = [0usize; MAX_COLOURS as usize];
+        let mut in_secret = [0usize; MAX_COLOURS as usize];
+        for (&a, &b) in guess.0.iter().zip(secret.0.iter()) {
+            in_guess[a as usize] += 1;
+            in_secret[b as usize] += 1;
+        }
+        let common: usize = in_guess
+            .iter()
+            .zip(in_secret.iter())
+            .map(|(a, b)| a.min(b))
+            .sum();
+
+        Feedback {
+            black,
+            white: common - black,
+        }
+    }
+}
+
+impl fmt::Display for Feedback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "black {}, white {}", self.black, self.white)
+    }
+}
+
+pub struct Solver {
+    pegs: usize,
+    all: Vec<Code>,
+    // the codes that fit every answer so far
+    candidates: Vec<Code>,
+}
+
+impl Solver {
+    pub fn new(pegs: usize, colours: u8) -> Result<Solver, String> {
+        if (colours as usize)
+            .checked_pow(pegs as u32)
+            .is_none_or(|count| count > SOLVER_LIMIT)
+        {
+            return Err(format!(
+                "The solver handles up to {} codes, {} pegs in {} colours are more.",
+                SOLVER_LIMIT, pegs, colours
+            ));
+        }
+
+        let all = Code::all(pegs, colours);
+        Ok(Solver {
+            pegs,
+            candidates: all.clone(),
+            all,
+        })
+    }
+
+    pub fn candidates(&self) -> &[Code] {
+        &self.candidates
+    }
+
+    pub fn learn(&mut self, guess: &Code, feedback: Feedback) {
+        self.candidates
+            .retain(|candidate| Feedback::of(guess, candidate) == feedback);
+    }
+
+    // None once no code fits the answers, which means one of them was wrong
+    pub fn suggest(&self) -> Option<Code> {
+        match self.candidates.len() {
+            0 => return None,
+            1 => return Some(self.candidates[0].clone()),
+            _ => {}
+        }
+        if self.candidates.len() == self.all.len() {
+            let half = self.pegs / 2;
+            return Some(Code(
+                (0..self.pegs).map(|peg| (peg >= half) as u8).collect(),
+            ));
+        }
+
+        let mut best: Option<(usize, bool, &Code)> = None;
+        for guess in &self.all {
+            let worst = self.worst_case(guess);
+            let possible = self.candidates.contains(guess);
+            let better = match best {
+                None => true,
+                // fewer left in the worst case, then a guess that could win
+                Some((best_worst, best_possible, _)) => {
+                    worst < best_worst || (worst == best_worst && possible && !best_possible)
+                }
+            };
+            if better {
+                best = Some((worst, possible, guess));
+            }
+        }
+
+        best.map(|(_, _, guess)| guess.clone())
+    }
+
+    // The most candidates any one answer to guess would leave
+    fn worst_case(&self, guess: &Code) -> usize {
+        let mut left = vec![0usize; (self.pegs + 1) * (self.pegs + 1)];
+        for candidate in &self.candidates {
+            let feedback = Feedback::of(guess, candidate);
+            left[feedback.black * (self.pegs + 1) + feedback.white] += 1;
+        }
+        left.into_iter().max().unwrap_or(0)
+    }
+}
+
+pub struct Mastermind {
+    pegs: usize,
+    colours: u8,
+    max_attempts: Option<u32>,
+    secret: Code,
+    history: Vec<(Code, Feedback)>,
+    state: State,
+    messages: Catalogue,
+}
+
+impl Mastermind {
+    pub fn new(config: &Config, pegs: usize, colours: u8, seed: u64) -> Mastermind {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let secret = Code(
+            (0..pegs)
+                .map(|_| rng.sample(Uniform::new(0, colours)))
+                .collect(),
+        );
+        Mastermind::with_secret(config, colours, secret)
+    }
+
+    pub fn with_secret(config: &Config, colours: u8, secret: Code) -> Mastermind {
+        Mastermind {
+            pegs: secret.0.len(),
+            colours,
+            max_attempts: config.max_attempts,
+            secret,
+            history: Vec::new(),
+            state: State::Playing,
+            messages: Catalogue::new(config.language.unwrap_or_default()),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn secret(&self) -> &Code {
+        &self.secret
+    }
+
+    pub fn history(&self) -> &[(Code, Feedback)] {
+        &self.history
+    }
+
+    pub fn guess(&mut self, guess: Code) -> Feedback {
+        assert_eq!(State::Playing, self.state, "the game is over");
+
+        let feedback = Feedback::of(&guess, &self.secret);
+        self.history.push((guess, feedback));
+
+        if feedback.black == self.pegs {
+            self.state = State::Won;
+        } else if self.max_attempts == Some(self.history.len() as u32) {
+            self.state = State::Lost;
+        }
+        feedback
+    }
+
+    // What the solver would guess next, knowing what the player has been told
+    pub fn suggest(&self) -> Result<Code, String> {
+        let mut solver = Solver::new(self.pegs, self.colours)?;
+        for (guess, feedback) in &self.history {
+            solver.learn(guess, *feedback);
+        }
+        solver
+            .suggest()
+            .ok_or_else(|| self.messages.text("mastermind.no-fit", &[]))
+    }
+
+    // black 1, white 2
+    fn answer(&self, feedback: Feedback) -> String {
+        self.messages.text(
+            "mastermind.answer",
+            &[("black", &feedback.black), ("white", &feedback.white)],
+        )
+    }
+
+    fn write_end<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let code = [("code", &self.secret as &dyn fmt::Display)];
+        match self.state {
+            State::Won => {
+                let guesses = self.history.len() as u64;
+                writeln!(
+                    out,
+                    "{}",
+                    self.messages.plural("mastermind.won", guesses, &[])
+                )
+            }
+            State::Lost => writeln!(out, "{}", self.messages.text("mastermind.lost", &code)),
+            _ => Ok(()),
+        }
+    }
+
+    // Plays until the code is cracked, the guesses run out or input does
+    pub fn play<R: BufRead, W: Write>(&mut self, mut input: R, out: &mut W) -> io::Result<State> {
+        let messages = self.messages;
+
+        writeln!(
+            out,
+            "{}",
+            messages.text(
+                "mastermind.intro",
+                &[("pegs", &self.pegs), ("colours", &self.colours)]
+            )
+        )?;
+        if let Some(max_attempts) = self.max_attempts {
+            writeln!(
+                out,
+                "{}",
+                messages.plural("guesses", max_attempts.into(), &[])
+            )?;
+        }
+        writeln!(out, "{}", messages.text("mastermind.commands", &[]))?;
+
+        while self.state == State::Playing {
+            writeln!(out, "{}", messages.text("prompt", &[]))?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 || line.trim() == "quit" {
+                self.state = State::Quit;
+                let bye = messages.text("mastermind.bye", &[("code", &self.secret)]);
+                writeln!(out, "{}", bye)?;
+                break;
+            }
+
+            if line.trim() == "suggest" {
+                match self.suggest() {
+                    Ok(code) => writeln!(
+                        out,
+                        "{}",
+                        messages.text("mastermind.try", &[("code", &code)])
+                    )?,
+                    Err(why) => writeln!(out, "{}", why)?,
+                }
+                continue;
+            }
+
+            match Code::parse(&line, self.pegs, self.colours, &messages) {
+                Ok(guess) => {
+                    let feedback = self.guess(guess);
+                    writeln!(out, "{}", self.answer(feedback))?;
+                }
+                Err(why) => writeln!(out, "{}", why)?,
+            }
+        }
+
+        self.write_end(out)?;
+        Ok(self.state)
+    }
+
+    // Lets the solver play to the end, printing every guess
+    pub fn solve<W: Write>(&mut self, out: &mut W) -> Result<State, Box<dyn std::error::Error>> {
+        let mut solver = Solver::new(self.pegs, self.colours)?;
+
+        while self.state == State::Playing {
+            let guess = solver
+                .suggest()
+                .ok_or_else(|| self.messages.text("mastermind.no-fit", &[]))?;
+            let feedback = self.guess(guess.clone());
+            let answer = self.answer(feedback);
+            writeln!(
+                out,
+                "{}",
+                self.messages.text(
+                    "mastermind.solver",
+                    &[("code", &guess), ("answer", &answer)]
+                )
+            )?;
+            solver.learn(&guess, feedback);
+        }
+
+        self.write_end(out)?;
+        Ok(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(text: &str) -> Code {
+        Code::parse(text, text.len(), MAX_COLOURS, &Catalogue::default()).unwrap()
+    }
+
+    fn feedback(guess: &str, secret: &str) -> (usize, usize) {
+        let feedback = Feedback::of(&code(guess), &code(secret));
+        (feedback.black, feedback.white)
+    }
+
+    fn config() -> Config {
+        Config::new(std::iter::empty()).unwrap()
+    }
+
+    #[test]
+    fn black_and_white_pegs() {
+        assert_eq!((4, 0), feedback("1234", "1234"));
+        assert_eq!((0, 4), feedback("4321", "1234"));
+        assert_eq!((0, 0), feedback("5566", "1234"));
+        assert_eq!((2, 2), feedback("1243", "1234"));
+        // a colour counts only as often as the code has it
+        assert_eq!((1, 0), feedback("1111", "1234"));
+        assert_eq!((1, 1), feedback("1122", "1314"));
+        assert_eq!((0, 4), feedback("1122", "2211"));
+        assert_eq!((2, 0), feedback("1122", "1155"));
+        assert_eq!((0, 3), feedback("1223", "2131"));
+    }
+
+    #[test]
+    fn codes_are_parsed() {
+        let english = Catalogue::default();
+        assert_eq!(
+            Ok(Code(vec![0, 5, 2, 2])),
+            Code::parse(" 1633\n", 4, 6, &english)
+        );
+        assert!(Code::parse("1237", 4, 6, &english).is_err());
+        assert!(Code::parse("123", 4, 6, &english).is_err());
+        assert!(Code::parse("12a4", 4, 6, &english).is_err());
+        assert_eq!("1633", Code(vec![0, 5, 2, 2]).to_string());
+    }
+
+    #[test]
+    fn every_code() {
+        let all = Code::all(2, 3);
+
+        assert_eq!(9, all.len());
+        assert_eq!(code("11"), all[0]);
+        assert_eq!(code("23"), all[5]);
+        assert_eq!(code("33"), all[8]);
+    }
+
+    #[test]
+    fn knuth_needs_at_most_five_guesses() {
+        // every 131st of the 1296 codes, to keep the test quick
+        for secret in Code::all(4, 6).into_iter().step_by(131) {
+            let mut game = Mastermind::with_secret(&config(), 6, secret.clone());
+            let state = game.solve(&mut Vec::new()).unwrap();
+
+            assert_eq!(State::Won, state, "{}", secret);
+            assert!(
+                game.history().len() <= 5,
+                "{} took {}",
+                secret,
+                game.history().len()
+            );
+            assert_eq!(code("1122"), game.history()[0].0);
+        }
+    }
+
+    #[test]
+    fn solver_cracks_every_small_code() {
+        for secret in Code::all(3, 3) {
+            let mut game = Mastermind::with_secret(&config(), 3, secret);
+            assert_eq!(State::Won, game.solve(&mut Vec::new()).unwrap());
+        }
+    }
+
+    #[test]
+    fn suggestions_fit_the_answers() {
+        let mut game = Mastermind::with_secret(&config(), 6, code("3456"));
+        assert_eq!(Ok(code("1122")), game.suggest());
+
+        game.guess(code("1234"));
+        let suggestion = game.suggest().unwrap();
+        assert_ne!(code("1234"), suggestion);
+
+        let mut solver = Solver::new(4, 6).unwrap();
+        solver.learn(&code("1234"), Feedback::of(&code("1234"), &code("3456")));
+        assert!(solver.candidates().contains(&code("3456")));
+        assert!(solver.candidates().len() < 1296);
+
+        // nothing fits contradicting answers
+        solver.learn(&code("1234"), Feedback { black: 4, white: 0 });
+        assert_eq!(None, solver.suggest());
+    }
+
+    #[test]
+    fn solver_has_a_limit() {
+        assert!(Solver::new(4, 9).is_ok());
+        assert!(Solver::new(8, 9).is_err());
+    }
+
+    #[test]
+    fn session() {
+        let mut game = Mastermind::with_secret(&config(), 6, code("3456"));
+        let mut out = Vec::new();
+        let state = game
+            .play("1234\n12\nsuggest\n3456\n".as_bytes(), &mut out)
+            .unwrap();
+
+        assert_eq!(State::Won, state);
+        let suggestion = {
+            let mut game = Mastermind::with_secret(&config(), 6, code("3456"));
+            game.guess(code("1234"));
+            game.suggest().unwrap()
+        };
+        assert_eq!(
+            format!(
+                "\
+Crack the code of 4 pegs in the colours 1 to 6.
+You have 10 guesses.
+Type suggest for a hint, or quit.
+Please input your guess.
+black 0, white 2
+Please input your guess.
+A code has 4 pegs, not 2.
+Please input your guess.
+Try {}.
+Please input your guess.
+black 4, white 0
+Cracked in 2!
+",
+                suggestion
+            ),
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,574 @@
+// serve and join: everybody on the network guesses the same number.
+//
+//     first_demo serve --port=7878          # on one machine
+//     first_demo join 192.168.1.20:7878     # on everyone's machine
+//
+// The server plays rounds. Every player gets their own Game for the round, all seeded
+// alike, so they share the secret but each has their own guesses (and limit). The first to
+// guess right wins the round, and the next one starts straight away. If everyone runs out of
+// guesses first, the number is revealed and the next round starts too.
+//
+// The protocol is lines of text over TCP, a word first. The client sends
+//
+//     NAME <name>                 once, right after connecting
+//     GUESS <number>
+//
+// and the server sends
+//
+//     ROUND <round> <min> <max>   a round started
+//     JOIN <name>                 somebody joined
+//     LEAVE <name>                somebody left
+//     HINT <guess> too-small|too-big <name>
+//     WIN <guess> <attempts> <name>
+//     REVEAL <secret>             nobody got it
+//     ERROR <code> <arguments>    to one player only, one of
+//         name-first                  the first line wasn't NAME
+//         unknown-command <line>
+//         not-a-number <text>
+//         out-of-guesses              wait for the next round
+//         range <min> <max>
+//         repeated <guess>
+//
+// Names and text the player typed go last so they can have spaces in them. HINT and WIN go to every player, so
+// everyone learns from everyone else's guesses. join turns these into sentences.
+
+use crate::config::Config;
+use crate::game::{Feedback, Game, State};
+use crate::locale::Catalogue;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// A player who stops reading shouldn't hold up everyone else for longer than this
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Player {
+    name: String,
+    out: TcpStream,
+    game: Game,
+}
+
+struct Room {
+    config: Config,
+    seed: u64,
+    round: u64,
+    next_id: usize,
+    players: HashMap<usize, Player>,
+}
+
+impl Room {
+    // Every player's game in a round is seeded alike, so they all have the same secret
+    fn game(&self) -> Game {
+        Game::new(self.config.clone(), self.seed.wrapping_add(self.round))
+    }
+
+    fn round_line(&self) -> String {
+        format!(
+            "ROUND {} {} {}",
+            self.round, self.config.min, self.config.max
+        )
+    }
+
+    // Writes line to the player; one that can't be written to will be gone soon anyway
+    fn send(&mut self, id: usize, line: &str) {
+        if let Some(player) = self.players.get_mut(&id) {
+            let _ = writeln!(player.out, "{}", line);
+        }
+    }
+
+    fn broadcast(&mut self, line: &str) {
+        for player in self.players.values_mut() {
+            let _ = writeln!(player.out, "{}", line);
+        }
+    }
+
+    fn join(&mut self, name: String, out: TcpStream) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.broadcast(&format!("JOIN {}", name));
+        let game = self.game();
+        self.players.insert(id, Player { name, out, game });
+        let round = self.round_line();
+        self.send(id, &round);
+
+        id
+    }
+
+    fn leave(&mut self, id: usize) {
+        if let Some(player) = self.players.remove(&id) {
+            let _ = player.out.shutdown(Shutdown::Both);
+            self.broadcast(&format!("LEAVE {}", player.name));
+            // the one who left may have been the last still guessing
+            self.reveal_if_everyone_lost();
+        }
+    }
+
+    // Nobody can win the round any more, so it ends without a winner
+    fn reveal_if_everyone_lost(&mut self) {
+        if !self.players.is_empty()
+            && self
+                .players
+                .values()
+                .all(|player| player.game.state() == State::Lost)
+        {
+            let secret = self.game().secret();
+            self.broadcast(&format!("REVEAL {}", secret));
+            self.next_round();
+        }
+    }
+
+    fn next_round(&mut self) {
+        self.round += 1;
+        for id in self.players.keys().copied().collect::<Vec<usize>>() {
+            let game = self.game();
+            self.players.get_mut(&id).unwrap().game = game;
+        }
+        let round = self.round_line();
+        self.broadcast(&round);
+    }
+
+    fn guess(&mut self, id: usize, text: &str) {
+        let guess: u32 = match text.trim().parse() {
+            Ok(guess) => guess,
+            Err(_) => return self.send(id, &format!("ERROR not-a-number {}", text.trim())),
+        };
+
+        let player = self.players.get_mut(&id).unwrap();
+        if player.game.state() != State::Playing {
+            return self.send(id, "ERROR out-of-guesses");
+        }
+
+        let feedback = player.game.guess(guess);
+        let (attempts, name, state) = (
+            player.game.attempts(),
+            player.name.clone(),
+            player.game.state(),
+        );
+        match feedback {
+            Feedback::OutOfRange => {
+                let line = format!("ERROR range {} {}", self.config.min, self.config.max);
+                self.send(id, &line);
+            }
+            Feedback::Repeated => {
+                self.send(id, &format!("ERROR repeated {}", guess));
+            }
+            Feedback::TooSmall | Feedback::TooBig => {
+                let hint = if feedback == Feedback::TooSmall {
+                    "too-small"
+                } else {
+                    "too-big"
+                };
+                self.broadcast(&format!("HINT {} {} {}", guess, hint, name));
+
+                if state == State::Lost {
+                    self.send(id, "ERROR out-of-guesses");
+                }
+                self.reveal_if_everyone_lost();
+            }
+            Feedback::Correct => {
+                self.broadcast(&format!("WIN {} {} {}", guess, attempts, name));
+                self.next_round();
+            }
+        }
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    room: Arc<Mutex<Room>>,
+}
+
+impl Server {
+    // Rounds are seeded seed + 1, seed + 2..., so a --seed gives the same secrets every time
+    pub fn bind<A: ToSocketAddrs>(address: A, config: Config, seed: u64) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            room: Arc::new(Mutex::new(Room {
+                config,
+                seed,
+                round: 1,
+                next_id: 0,
+                players: HashMap::new(),
+            })),
+        })
+    }
+
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    // Takes players until the process ends, each on a thread of its own
+    pub fn run(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let room = Arc::clone(&self.room);
+            thread::spawn(move || {
+                // A player's connection failing is only that player's problem
+                let _ = handle(room, stream);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn handle(room: Arc<Mutex<Room>>, stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut lines = BufReader::new(stream.try_clone()?).lines();
+
+    let name = match lines.next() {
+        Some(line) => match line?.strip_prefix("NAME ") {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => return writeln!(&stream, "ERROR name-first"),
+        },
+        None => return Ok(()),
+    };
+    let id = room.lock().unwrap().join(name, stream);
+
+    let mut result = Ok(());
+    for line in lines {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+
+        let mut room = room.lock().unwrap();
+        match line.strip_prefix("GUESS ") {
+            Some(guess) => room.guess(id, guess),
+            None => room.send(id, &format!("ERROR unknown-command {}", line)),
+        }
+    }
+
+    room.lock().unwrap().leave(id);
+    result
+}
+
+// A line from the server as a sentence for the player, in the player's language.
+// The protocol itself is the same whatever language anyone plays in.
+pub fn render(line: &str, messages: &Catalogue) -> String {
+    let mut words = line.splitn(2, ' ');
+    let kind = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("");
+    let fields: Vec<&str> = rest.splitn(3, ' ').collect();
+    // what the player typed, after an ERROR code, spaces and all
+    let typed = || format!("{:?}", rest.split_once(' ').map_or("", |(_, typed)| typed));
+
+    match (kind, fields.as_slice()) {
+        ("ROUND", [round, min, max]) => messages.text(
+            "multiplayer.round",
+            &[("round", round), ("min", min), ("max", max)],
+        ),
+        ("JOIN", _) => messages.text("multiplayer.joined", &[("name", &rest)]),
+        ("LEAVE", _) => messages.text("multiplayer.left", &[("name", &rest)]),
+        ("HINT", [guess, hint @ ("too-small" | "too-big"), name]) => {
+            let hint = messages.text(&format!("number.history.{}", hint), &[]);
+            messages.text(
+                "multiplayer.hint",
+                &[("name", name), ("guess", guess), ("hint", &hint)],
+            )
+        }
+        ("WIN", [guess, attempts, name]) => match attempts.parse() {
+            Ok(attempts) => messages.plural(
+                "multiplayer.won",
+                attempts,
+                &[("name", name), ("guess", guess)],
+            ),
+            Err(_) => line.to_string(),
+        },
+        ("REVEAL", _) => messages.text("multiplayer.reveal", &[("secret", &rest)]),
+        ("ERROR", [code @ ("name-first" | "out-of-guesses")]) => {
+            messages.text(&format!("multiplayer.error.{}", code), &[])
+        }
+        ("ERROR", ["unknown-command", ..]) => messages.text(
+            "multiplayer.error.unknown-command",
+            &[("command", &typed())],
+        ),
+        ("ERROR", ["not-a-number", ..]) => {
+            messages.text("multiplayer.error.not-a-number", &[("text", &typed())])
+        }
+        ("ERROR", ["range", min, max]) => {
+            messages.text("multiplayer.error.range", &[("min", min), ("max", max)])
+        }
+        ("ERROR", ["repeated", guess]) => {
+            messages.text("multiplayer.error.repeated", &[("guess", guess)])
+        }
+        // something a newer server sends
+        _ => line.to_string(),
+    }
+}
+
+// Plays on the server at address: lines from input are sent as guesses, and what the
+// server says is written to out, until the server closes the connection
+pub fn join<R, W>(
+    address: &str,
+    name: &str,
+    messages: &Catalogue,
+    input: R,
+    out: &mut W,
+) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    let stream = TcpStream::connect(address)?;
+    let mut to_server = stream.try_clone()?;
+    writeln!(to_server, "NAME {}", name)?;
+
+    thread::spawn(move || {
+        for line in input.lines() {
+            let sent = line.and_then(|line| writeln!(to_server, "GUESS {}", line.trim()));
+            if sent.is_err() {
+                break;
+            }
+        }
+        // done guessing; the server hangs up in turn, which ends the loop below
+        let _ = to_server.shutdown(Shutdown::Write);
+    });
+
+    for line in BufReader::new(stream).lines() {
+        writeln!(out, "{}", render(&line?, messages))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Language;
+
+    fn config() -> Config {
+        Config::new(vec![String::from("--max-attempts=2")].into_iter()).unwrap()
+    }
+
+    struct Client {
+        lines: io::Lines<BufReader<TcpStream>>,
+        out: TcpStream,
+    }
+
+    impl Client {
+        fn connect(port: u16, name: &str) -> Client {
+            let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            // a test that goes wrong fails instead of waiting forever
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut client = Client {
+                out: stream.try_clone().unwrap(),
+                lines: BufReader::new(stream).lines(),
+            };
+            client.send(&format!("NAME {}", name));
+            client
+        }
+
+        fn send(&mut self, line: &str) {
+            writeln!(self.out, "{}", line).unwrap();
+        }
+
+        fn receive(&mut self) -> String {
+            self.lines.next().unwrap().unwrap()
+        }
+    }
+
+    // A server on a free port, and the secret of each of its first rounds
+    fn start(seed: u64) -> (u16, Vec<u32>) {
+        let server = Server::bind("127.0.0.1:0", config(), seed).unwrap();
+        let port = server.local_port().unwrap();
+        thread::spawn(move || server.run());
+
+        let secrets = (1..=3)
+            .map(|round| Game::new(config(), seed + round).secret())
+            .collect();
+        (port, secrets)
+    }
+
+    fn wrong(secret: u32) -> u32 {
+        if secret == 1 {
+            2
+        } else {
+            1
+        }
+    }
+
+    #[test]
+    fn players_share_hints_and_the_winner() {
+        let (port, secrets) = start(10);
+
+        let mut alice = Client::connect(port, "alice");
+        assert_eq!("ROUND 1 1 100", alice.receive());
+        let mut bob = Client::connect(port, "bob the builder");
+        assert_eq!("ROUND 1 1 100", bob.receive());
+        assert_eq!("JOIN bob the builder", alice.receive());
+
+        bob.send("GUESS ten");
+        assert_eq!("ERROR not-a-number ten", bob.receive());
+        bob.send("GUESS 500");
+        assert_eq!("ERROR range 1 100", bob.receive());
+        bob.send("PASS");
+        assert_eq!("ERROR unknown-command PASS", bob.receive());
+
+        let guess = wrong(secrets[0]);
+        let hint = if guess < secrets[0] {
+            "too-small"
+        } else {
+            "too-big"
+        };
+        bob.send(&format!("GUESS {}", guess));
+        let expected = format!("HINT {} {} bob the builder", guess, hint);
+        assert_eq!(expected, alice.receive());
+        assert_eq!(expected, bob.receive());
+
+        alice.send(&format!("GUESS {}", secrets[0]));
+        for client in [&mut alice, &mut bob] {
+            assert_eq!(format!("WIN {} 1 alice", secrets[0]), client.receive());
+            assert_eq!("ROUND 2 1 100", client.receive());
+        }
+
+        drop(bob);
+        assert_eq!("LEAVE bob the builder", alice.receive());
+    }
+
+    #[test]
+    fn round_restarts_when_everyone_is_out_of_guesses() {
+        let (port, secrets) = start(20);
+        let mut alice = Client::connect(port, "alice");
+        assert_eq!("ROUND 1 1 100", alice.receive());
+
+        let guess = wrong(secrets[0]);
+        alice.send(&format!("GUESS {}", guess));
+        assert!(alice.receive().starts_with("HINT "));
+        alice.send(&format!("GUESS {}", guess));
+        assert_eq!(format!("ERROR repeated {}", guess), alice.receive());
+
+        let other = (1..=3)
+            .find(|&other| other != guess && other != secrets[0])
+            .unwrap();
+        alice.send(&format!("GUESS {}", other));
+        assert!(alice.receive().starts_with("HINT "));
+        assert_eq!("ERROR out-of-guesses", alice.receive());
+        assert_eq!(format!("REVEAL {}", secrets[0]), alice.receive());
+        assert_eq!("ROUND 2 1 100", alice.receive());
+    }
+
+    #[test]
+    fn round_restarts_when_the_last_one_guessing_leaves() {
+        let (port, secrets) = start(25);
+        let mut alice = Client::connect(port, "alice");
+        assert_eq!("ROUND 1 1 100", alice.receive());
+        let mut bob = Client::connect(port, "bob");
+        assert_eq!("ROUND 1 1 100", bob.receive());
+        assert_eq!("JOIN bob", alice.receive());
+
+        let mut wrong_guesses = (1..=3).filter(|&guess| guess != secrets[0]);
+        for _ in 0..2 {
+            alice.send(&format!("GUESS {}", wrong_guesses.next().unwrap()));
+            assert!(alice.receive().starts_with("HINT "));
+        }
+        assert_eq!("ERROR out-of-guesses", alice.receive());
+
+        drop(bob);
+        assert_eq!("LEAVE bob", alice.receive());
+        assert_eq!(format!("REVEAL {}", secrets[0]), alice.receive());
+        assert_eq!("ROUND 2 1 100", alice.receive());
+    }
+
+    #[test]
+    fn name_comes_first() {
+        let (port, _) = start(30);
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        writeln!(&stream, "GUESS 5").unwrap();
+
+        let mut lines = BufReader::new(stream).lines();
+        assert_eq!("ERROR name-first", lines.next().unwrap().unwrap());
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn join_renders_the_server() {
+        let (port, secrets) = start(40);
+        let input = format!("{}\n", secrets[0]);
+        let mut out = Vec::new();
+
+        join(
+            &format!("127.0.0.1:{}", port),
+            "carol",
+            &Catalogue::default(),
+            io::Cursor::new(input),
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!(
+                "\
+Round 1: guess a number between 1 and 100.
+carol wins with {} after 1 guess!
+Round 2: guess a number between 1 and 100.
+",
+                secrets[0]
+            ),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn rendering() {
+        let english = Catalogue::default();
+        assert_eq!(
+            "bob the builder guessed 40: too small!",
+            render("HINT 40 too-small bob the builder", &english)
+        );
+        assert_eq!(
+            "Nobody got it. The number was 7.",
+            render("REVEAL 7", &english)
+        );
+        assert_eq!(
+            "bob wins with 7 after 3 guesses!",
+            render("WIN 7 3 bob", &english)
+        );
+        assert_eq!("SOMETHING new", render("SOMETHING new", &english));
+        assert_eq!(
+            "HINT 40 lukewarm bob",
+            render("HINT 40 lukewarm bob", &english)
+        );
+        assert_eq!(
+            "The number is between 1 and 100.",
+            render("ERROR range 1 100", &english)
+        );
+        assert_eq!(
+            "\"ten or 11\" is not a number.",
+            render("ERROR not-a-number ten or 11", &english)
+        );
+        assert_eq!("ERROR too-late 5", render("ERROR too-late 5", &english));
+
+        let german = Catalogue::new(Language::German);
+        assert_eq!(
+            "Runde 2: Rate eine Zahl zwischen 1 und 100.",
+            render("ROUND 2 1 100", &german)
+        );
+        assert_eq!(
+            "bob tippt 40: zu groß!",
+            render("HINT 40 too-big bob", &german)
+        );
+        assert_eq!(
+            "bob gewinnt mit 7 nach 1 Versuch!",
+            render("WIN 7 1 bob", &german)
+        );
+        assert_eq!(
+            "Du hast keine Tipps mehr, warte auf die nächste Runde.",
+            render("ERROR out-of-guesses", &german)
+        );
+        assert_eq!("5 hattest du schon.", render("ERROR repeated 5", &german));
+
+        let chinese = Catalogue::new(Language::Chinese);
+        assert_eq!(
+            "服务器不认识命令 \"PASS\"。",
+            render("ERROR unknown-command PASS", &chinese)
+        );
+    }
+}
@@ -0,0 +1,521 @@
+// --record and replay: keep a session to play it again.
+//
+//     first_demo --difficulty=hard --record session.log   # play, and keep the session
+//     first_demo replay session.log                        # play it again, check it's the same
+//
+// A session log is text, with a line for everything that was printed or typed:
+//
+//     first_demo session
+//     seed 8417306429514716
+//     arg --difficulty=hard
+//     0.000 out Guess the number!
+//     ...
+//     0.000 out Please input your guess.
+//     3.104 in 500
+//     3.104 out You guessed: 500
+//     3.104 out Too big!
+//
+// The seed and the args make the same game again, so replay feeds it the lines after
+// in and checks that what it prints is what the lines after out say. part is printed
+// text that didn't end its line before the player typed, like the prompt of words.
+// The times are seconds since the start, for people reading the log; replay ignores
+// them. --record itself isn't kept in the args, --daily words keep the day's seed and
+// the language is kept as --lang, so replaying doesn't record again, pick today's word
+// or speak whatever LANG says where it is replayed.
+//
+// Bug reports can come with a log, and the logs in tests/sessions are replayed by
+// cargo test.
+
+use crate::config::{Command, Config};
+use crate::locale::Language;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+const HEADER: &str = "first_demo session";
+
+struct Recorder {
+    log: Box<dyn Write>,
+    started: Instant,
+    // typed and printed bytes that haven't made a whole line yet
+    typed: Vec<u8>,
+    printed: Vec<u8>,
+    // reading can't fail because of the log, so its first error waits for finish
+    failed: Option<io::Error>,
+}
+
+impl Recorder {
+    fn entry(&mut self, kind: &str, bytes: &[u8]) {
+        if self.failed.is_some() {
+            return;
+        }
+        let text = String::from_utf8_lossy(bytes);
+        let seconds = self.started.elapsed().as_secs_f64();
+        if let Err(e) = writeln!(self.log, "{:.3} {} {}", seconds, kind, text) {
+            self.failed = Some(e);
+        }
+    }
+
+    fn printed(&mut self, bytes: &[u8]) {
+        self.printed.extend_from_slice(bytes);
+        while let Some(end) = self.printed.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.printed.drain(..=end).collect();
+            self.entry("out", &line[..end]);
+        }
+    }
+
+    fn typed(&mut self, bytes: &[u8]) {
+        self.typed.extend_from_slice(bytes);
+        while let Some(end) = self.typed.iter().position(|&byte| byte == b'\n') {
+            // the prompt comes before what was typed after it
+            self.part();
+            let line: Vec<u8> = self.typed.drain(..=end).collect();
+            self.entry("in", &line[..end]);
+        }
+    }
+
+    fn part(&mut self) {
+        if !self.printed.is_empty() {
+            let part = std::mem::take(&mut self.printed);
+            self.entry("part", &part);
+        }
+    }
+}
+
+// A session being recorded: the game reads through input and prints through output
+pub struct Recording {
+    recorder: Rc<RefCell<Recorder>>,
+}
+
+impl Recording {
+    pub fn start<L: Write + 'static>(
+        mut log: L,
+        seed: u64,
+        args: &[String],
+    ) -> io::Result<Recording> {
+        writeln!(log, "{}", HEADER)?;
+        writeln!(log, "seed {}", seed)?;
+        for arg in args {
+            writeln!(log, "arg {}", arg)?;
+        }
+
+        Ok(Recording {
+            recorder: Rc::new(RefCell::new(Recorder {
+                log: Box::new(log),
+                started: Instant::now(),
+                typed: Vec::new(),
+                printed: Vec::new(),
+                failed: None,
+            })),
+        })
+    }
+
+    pub fn create(path: &Path, seed: u64, args: &[String]) -> io::Result<Recording> {
+        let file = fs::File::create(path)?;
+        Recording::start(io::BufWriter::new(file), seed, args)
+    }
+
+    pub fn input<R: BufRead>(&self, inner: R) -> Input<R> {
+        Input {
+            inner,
+            recorder: Rc::clone(&self.recorder),
+        }
+    }
+
+    pub fn output<W: Write>(&self, inner: W) -> Output<W> {
+        Output {
+            inner,
+            recorder: Rc::clone(&self.recorder),
+        }
+    }
+
+    // Writes what is left of the session, a line typed or printed without a newline
+    pub fn finish(self) -> io::Result<()> {
+        let mut recorder = self.recorder.borrow_mut();
+        recorder.part();
+        if !recorder.typed.is_empty() {
+            let typed = std::mem::take(&mut recorder.typed);
+            recorder.entry("in", &typed);
+        }
+        if let Some(e) = recorder.failed.take() {
+            return Err(e);
+        }
+        recorder.log.flush()
+    }
+}
+
+pub struct Input<R> {
+    inner: R,
+    recorder: Rc<RefCell<Recorder>>,
+}
+
+impl<R: BufRead> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Input<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    // What the game consumes is what was typed. The bytes are still in the buffer
+    // fill_buf gave out, so asking for it again doesn't read any more. At the end of
+    // the input there is nothing in it, and asking would wait for a terminal to be
+    // typed at a second time.
+    fn consume(&mut self, amount: usize) {
+        if amount > 0 {
+            if let Ok(buf) = self.inner.fill_buf() {
+                self.recorder.borrow_mut().typed(&buf[..amount]);
+            }
+        }
+        self.inner.consume(amount);
+    }
+}
+
+pub struct Output<W> {
+    inner: W,
+    recorder: Rc<RefCell<Recorder>>,
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.recorder.borrow_mut().printed(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// The args of a session: the ones given without --record, and the language it is played
+// in, which may have come from the environment rather than --lang
+pub fn session_args(args: &[String], language: Language) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--record" {
+            args.next();
+        } else if !arg.starts_with("--record=") && !arg.starts_with("--lang=") {
+            kept.push(arg.clone());
+        }
+    }
+    kept.push(format!("--lang={}", language.code()));
+    kept
+}
+
+// A recorded session, read back
+#[derive(Debug, PartialEq)]
+pub struct Session {
+    pub seed: u64,
+    pub args: Vec<String>,
+    // everything typed and everything printed, as it went in and came out
+    pub input: String,
+    pub output: String,
+}
+
+impl Session {
+    pub fn parse(text: &str) -> Result<Session, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line));
+
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(format!("doesn't start with {:?}", HEADER));
+        }
+        let seed = match lines.next() {
+            Some((number, line)) => line
+                .strip_prefix("seed ")
+                .and_then(|seed| seed.parse().ok())
+                .ok_or_else(|| format!("line {}: no seed", number))?,
+            None => return Err(String::from("line 2: no seed")),
+        };
+
+        let mut session = Session {
+            seed,
+            args: Vec::new(),
+            input: String::new(),
+            output: String::new(),
+        };
+        for (number, line) in lines {
+            if let Some(arg) = line.strip_prefix("arg ") {
+                session.args.push(arg.to_string());
+                continue;
+            }
+
+            // time, kind and text, which may be empty or have spaces of its own
+            let mut fields = line.splitn(3, ' ');
+            let time = fields.next().unwrap_or("");
+            let kind = fields.next().unwrap_or("");
+            let text = fields.next();
+            if time.parse::<f64>().is_err() || text.is_none() {
+                return Err(format!("line {}: bad entry {:?}", number, line));
+            }
+            let text = text.unwrap();
+
+            match kind {
+                "in" => {
+                    session.input.push_str(text);
+                    session.input.push('\n');
+                }
+                "out" => {
+                    session.output.push_str(text);
+                    session.output.push('\n');
+                }
+                "part" => session.output.push_str(text),
+                _ => return Err(format!("line {}: unknown entry {:?}", number, kind)),
+            }
+        }
+
+        Ok(session)
+    }
+
+    pub fn load(path: &Path) -> Result<Session, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Session::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Plays the session again and gives back what it printed this time
+    pub fn replay(&self) -> Result<String, Box<dyn Error>> {
+        let config = Config::new(self.args.iter().cloned())?;
+        if let Command::Replay(_) = config.command {
+            return Err("a session can't replay another one".into());
+        }
+
+        let mut out = Vec::new();
+        crate::play(&config, self.seed, self.input.as_bytes(), &mut out)?;
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+
+    // Replays the session and says where it went differently, or how many lines matched
+    pub fn check(&self) -> Result<usize, Box<dyn Error>> {
+        let replayed = self.replay()?;
+        if replayed == self.output {
+            return Ok(self.output.lines().count());
+        }
+
+        let mut recorded_lines = self.output.split_inclusive('\n');
+        let mut replayed_lines = replayed.split_inclusive('\n');
+        for number in 1.. {
+            let (recorded, replayed) = (recorded_lines.next(), replayed_lines.next());
+            if recorded != replayed {
+                let show = |line: Option<&str>| {
+                    line.map_or(String::from("(nothing)"), |line| format!("{:?}", line))
+                };
+                return Err(format!(
+                    "the replay differs at line {} of the output\n  recorded: {}\n  replayed: {}",
+                    number,
+                    show(recorded),
+                    show(replayed)
+                )
+                .into());
+            }
+        }
+        unreachable!("the outputs differ, so some line does")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::words::WordGame;
+    use std::sync::{Arc, Mutex};
+
+    // A log the test can read after the recording took it
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    // Records a session with args and typed, returning the log
+    fn record(args: &[&str], seed: u64, typed: &str) -> String {
+        let args = strings(args);
+        let config = Config::new(args.iter().cloned()).unwrap();
+        let log = SharedLog::default();
+
+        let args = session_args(&args, config.language.unwrap_or_default());
+        let recording = Recording::start(log.clone(), seed, &args).unwrap();
+        let input = recording.input(typed.as_bytes());
+        let mut out = recording.output(Vec::new());
+        crate::play(&config, seed, input, &mut out).unwrap();
+        recording.finish().unwrap();
+
+        let text = log.0.lock().unwrap().clone();
+        String::from_utf8(text).unwrap()
+    }
+
+    // The times change from run to run
+    fn without_times(log: &str) -> String {
+        log.lines()
+            .map(|line| match line.split_once(' ') {
+                Some((time, rest)) if time.parse::<f64>().is_ok() => rest.to_string(),
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn number_game_log() {
+        let log = record(&["--min=1", "--max=3", "--record", "x.log"], 5, "2\nquit\n");
+        let secret = Game::new(
+            Config::new(strings(&["--min=1", "--max=3"]).into_iter()).unwrap(),
+            5,
+        )
+        .secret();
+        assert!(log
+            .starts_with("first_demo session\nseed 5\narg --min=1\narg --max=3\narg --lang=en\n"));
+        assert!(log.contains("in 2\n"), "{}", log);
+        assert!(
+            without_times(&log).ends_with(&format!("in quit\nout Bye! The number was {}.", secret))
+        );
+
+        let session = Session::parse(&log).unwrap();
+        assert_eq!(5, session.seed);
+        assert_eq!("2\nquit\n", session.input);
+        assert!(session.check().unwrap() > 5);
+    }
+
+    #[test]
+    fn prompts_without_newline() {
+        let log = record(&["words"], 3, "crane\n");
+        let word = WordGame::random(3).answer().to_string();
+
+        assert!(
+            without_times(&log).contains("part Guess 1 of 6: \nin crane\nout "),
+            "{}",
+            log
+        );
+        // input ran out after the prompt for the second guess, which the game ends
+        // with a newline
+        assert!(without_times(&log).ends_with(&format!(
+            "out Guess 2 of 6: \nout Bye! The word was {}.",
+            word
+        )));
+        assert!(Session::parse(&log).unwrap().check().is_ok());
+    }
+
+    #[test]
+    fn changed_output_is_caught() {
+        let log = record(&["--min=1", "--max=100"], 11, "50\n25\n");
+        let changed = log.replace("out Please input your guess.", "out Please guess.");
+
+        let error = Session::parse(&changed)
+            .unwrap()
+            .check()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 5"), "{}", error);
+        assert!(
+            error.contains("recorded: \"Please guess.\\n\""),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("replayed: \"Please input your guess.\\n\""),
+            "{}",
+            error
+        );
+
+        // another seed is another secret, and other answers
+        let reseeded = log.replace("seed 11", "seed 12");
+        assert!(Session::parse(&reseeded).unwrap().check().is_err());
+    }
+
+    #[test]
+    fn bad_logs() {
+        assert!(Session::parse("").is_err());
+        assert!(Session::parse("first_demo session\nseed x\n").is_err());
+        assert!(Session::parse("first_demo session\nseed 1\n0.1 sideways hi\n").is_err());
+        assert!(Session::parse("first_demo session\nseed 1\nhello\n").is_err());
+        assert_eq!(
+            Session {
+                seed: 1,
+                args: strings(&["words"]),
+                input: String::from("abc\n"),
+                output: String::from("a\n\nb: "),
+            },
+            Session::parse("first_demo session\nseed 1\narg words\n0.0 out a\n0.0 out \n0.0 part b: \n1.5 in abc\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn record_is_not_kept() {
+        assert_eq!(
+            strings(&["words", "--daily", "--lang=zh"]),
+            session_args(
+                &strings(&[
+                    "--record",
+                    "a.log",
+                    "words",
+                    "--record=b.log",
+                    "--lang=zh",
+                    "--daily"
+                ]),
+                Language::Chinese
+            )
+        );
+    }
+
+    // Reads one line, then panics if it is read from again after telling of the end
+    struct Terminal {
+        lines: Vec<&'static [u8]>,
+        ended: bool,
+    }
+
+    impl io::Read for Terminal {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            assert!(!self.ended, "read again after the end of the input");
+            match self.lines.pop() {
+                Some(line) => {
+                    buf[..line.len()].copy_from_slice(line);
+                    Ok(line.len())
+                }
+                None => {
+                    self.ended = true;
+                    Ok(0)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn end_of_input_is_read_once() {
+        let recording = Recording::start(SharedLog::default(), 1, &[]).unwrap();
+        let terminal = Terminal {
+            lines: vec![b"abc\n"],
+            ended: false,
+        };
+        let mut input = recording.input(io::BufReader::new(terminal));
+
+        let mut line = String::new();
+        assert_eq!(4, input.read_line(&mut line).unwrap());
+        line.clear();
+        assert_eq!(0, input.read_line(&mut line).unwrap());
+    }
+}
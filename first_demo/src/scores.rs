@@ -0,0 +1,383 @@
+// The leaderboard: every finished game, won or lost, kept in a CSV file.
+//
+// The file lives in the user's data directory (~/.local/share/first_demo/scores.csv on
+// Linux), one game per row:
+//
+//     date,player,difficulty,min,max,attempts,seconds,won
+//     2024-03-01 12:00,alice,normal,1,100,6,21,true
+//
+// Games only ever append a row, under an exclusive lock on the file, and reading takes a
+// shared lock, so two games finishing at the same moment can't interleave their rows and
+// `first_demo scores` never sees half a row.
+//
+// `first_demo scores` shows, for each difficulty, the best won games: fewest guesses first,
+// then fastest. Games with a range or limit of their own are listed together as custom.
+
+use crate::config::{Config, Difficulty};
+use crate::game::{Game, State};
+use crate::locale::Catalogue;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const HEADER: [&str; 8] = [
+    "date",
+    "player",
+    "difficulty",
+    "min",
+    "max",
+    "attempts",
+    "seconds",
+    "won",
+];
+
+// Games shown per difficulty
+pub const TOP: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    // local time the game ended, as YYYY-MM-DD HH:MM
+    pub date: String,
+    pub player: String,
+    // easy, normal, hard or custom
+    pub difficulty: String,
+    pub min: u32,
+    pub max: u32,
+    pub attempts: u32,
+    pub seconds: u64,
+    pub won: bool,
+}
+
+impl Score {
+    // The score for a game that is over, or None while it is still being played.
+    // Giving up counts as losing.
+    pub fn of(config: &Config, game: &Game, taken: Duration) -> Option<Score> {
+        if game.state() == State::Playing {
+            return None;
+        }
+
+        Some(Score {
+            date: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            player: config.player_name(),
+            difficulty: config
+                .difficulty()
+                .map_or("custom", Difficulty::name)
+                .to_string(),
+            min: config.min,
+            max: config.max,
+            attempts: game.attempts(),
+            seconds: taken.as_secs(),
+            won: game.state() == State::Won,
+        })
+    }
+
+    fn to_record(&self) -> [String; 8] {
+        [
+            self.date.clone(),
+            self.player.clone(),
+            self.difficulty.clone(),
+            self.min.to_string(),
+            self.max.to_string(),
+            self.attempts.to_string(),
+            self.seconds.to_string(),
+            self.won.to_string(),
+        ]
+    }
+
+    fn from_record(record: &csv::StringRecord) -> Result<Score, String> {
+        if record.len() != HEADER.len() {
+            return Err(format!(
+                "expected {} fields, found {}",
+                HEADER.len(),
+                record.len()
+            ));
+        }
+
+        fn field<T: std::str::FromStr>(
+            record: &csv::StringRecord,
+            index: usize,
+        ) -> Result<T, String> {
+            record[index]
+                .parse()
+                .map_err(|_| format!("bad {} {:?}", HEADER[index], &record[index]))
+        }
+
+        Ok(Score {
+            date: record[0].to_string(),
+            player: record[1].to_string(),
+            difficulty: record[2].to_string(),
+            min: field(record, 3)?,
+            max: field(record, 4)?,
+            attempts: field(record, 5)?,
+            seconds: field(record, 6)?,
+            won: field(record, 7)?,
+        })
+    }
+}
+
+// Where scores are kept, unless the platform has no data directory
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("first_demo").join("scores.csv"))
+}
+
+pub fn append(path: &Path, score: &Score) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    // Held until file is dropped; the length has to be checked under it too, or two
+    // games could both find the file empty and both write the header
+    file.lock()?;
+
+    let mut writer = csv::Writer::from_writer(&file);
+    if file.metadata()?.len() == 0 {
+        writer.write_record(HEADER)?;
+    }
+    writer.write_record(score.to_record())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// Every game recorded in path, oldest first. No file yet means no games.
+pub fn load(path: &Path) -> Result<Vec<Score>, Box<dyn Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    file.lock_shared()?;
+
+    let mut scores = Vec::new();
+    for (index, record) in csv::Reader::from_reader(&file).records().enumerate() {
+        // index 0 is the row after the header, which is line 2
+        let score = Score::from_record(&record?)
+            .map_err(|e| format!("{}: line {}: {}", path.display(), index + 2, e))?;
+        scores.push(score);
+    }
+
+    Ok(scores)
+}
+
+// The difficulties in the order they are shown, with the best TOP won games of each
+pub fn leaderboard(scores: &[Score]) -> Vec<(&str, Vec<&Score>)> {
+    let mut board = Vec::new();
+
+    for difficulty in ["easy", "normal", "hard", "custom"] {
+        let mut won: Vec<&Score> = scores
+            .iter()
+            .filter(|score| score.won && score.difficulty == difficulty)
+            .collect();
+        // sort is stable, so equal games stay in the order they were played
+        won.sort_by_key(|score| (score.attempts, score.seconds));
+        won.truncate(TOP);
+
+        if !won.is_empty() {
+            board.push((difficulty, won));
+        }
+    }
+
+    board
+}
+
+pub fn write_leaderboard<W: Write>(
+    scores: &[Score],
+    messages: &Catalogue,
+    out: &mut W,
+) -> io::Result<()> {
+    let board = leaderboard(scores);
+    if board.is_empty() {
+        return writeln!(out, "{}", messages.text("scores.none", &[]));
+    }
+
+    // "guess" and "guesses" padded to the same width, so the columns after them line up
+    let width = [1, 2]
+        .iter()
+        .map(|&count| {
+            messages
+                .plural("scores.guesses", count, &[])
+                .chars()
+                .count()
+        })
+        .max()
+        .unwrap_or(0);
+
+    for (section, (difficulty, best)) in board.iter().enumerate() {
+        if section > 0 {
+            writeln!(out)?;
+        }
+        writeln!(
+            out,
+            "{}",
+            messages.text(&format!("scores.{}", difficulty), &[])
+        )?;
+
+        for (rank, score) in best.iter().enumerate() {
+            writeln!(
+                out,
+                "{:>3}. {:<16} {:>3} {:<width$} {:>5}s  {}-{}  {}",
+                rank + 1,
+                score.player,
+                score.attempts,
+                messages.plural("scores.guesses", score.attempts as u64, &[]),
+                score.seconds,
+                score.min,
+                score.max,
+                score.date,
+                width = width
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Language;
+    use std::thread;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("first_demo-{}-{}.csv", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn score(player: &str, difficulty: &str, attempts: u32, seconds: u64, won: bool) -> Score {
+        Score {
+            date: String::from("2024-03-01 12:00"),
+            player: player.to_string(),
+            difficulty: difficulty.to_string(),
+            min: 1,
+            max: 100,
+            attempts,
+            seconds,
+            won,
+        }
+    }
+
+    #[test]
+    fn append_and_load() {
+        let path = temp_path("append");
+        assert_eq!(Vec::<Score>::new(), load(&path).unwrap());
+
+        let first = score("alice", "normal", 6, 21, true);
+        // quoted by csv, and read back the same
+        let second = score("bob, \"the guesser\"", "hard", 10, 40, false);
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+
+        assert_eq!(vec![first, second], load(&path).unwrap());
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("date,player,difficulty,min,max,attempts,seconds,won\n"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_appends_keep_every_row() {
+        let path = temp_path("concurrent");
+
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    for game in 0..25 {
+                        let player = format!("player {}", writer);
+                        append(&path, &score(&player, "easy", game, 1, true)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(200, load(&path).unwrap().len());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn best_won_games_per_difficulty() {
+        let scores = vec![
+            score("slow", "normal", 5, 90, true),
+            score("fast", "normal", 5, 30, true),
+            score("lucky", "normal", 2, 5, true),
+            score("unlucky", "normal", 1, 1, false),
+            score("custom", "custom", 3, 3, true),
+        ];
+
+        let board = leaderboard(&scores);
+        let names: Vec<(&str, Vec<&str>)> = board
+            .iter()
+            .map(|(difficulty, best)| {
+                let players = best.iter().map(|score| score.player.as_str()).collect();
+                (*difficulty, players)
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("normal", vec!["lucky", "fast", "slow"]),
+                ("custom", vec!["custom"])
+            ],
+            names
+        );
+    }
+
+    #[test]
+    fn leaderboard_in_english_and_german() {
+        let scores = vec![
+            score("alice", "normal", 6, 21, true),
+            score("bob", "normal", 1, 3, true),
+        ];
+        let write = |scores: &[Score], language| {
+            let mut out = Vec::new();
+            write_leaderboard(scores, &Catalogue::new(language), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            "\
+normal
+  1. bob                1 guess       3s  1-100  2024-03-01 12:00
+  2. alice              6 guesses    21s  1-100  2024-03-01 12:00
+",
+            write(&scores, Language::English)
+        );
+        assert_eq!(
+            "\
+normal
+  1. bob                1 Versuch      3s  1-100  2024-03-01 12:00
+  2. alice              6 Versuche    21s  1-100  2024-03-01 12:00
+",
+            write(&scores, Language::German)
+        );
+        assert_eq!("No games won yet.\n", write(&[], Language::English));
+        assert_eq!("还没有赢过的游戏。\n", write(&[], Language::Chinese));
+    }
+
+    #[test]
+    fn broken_rows_are_reported() {
+        let path = temp_path("broken");
+        fs::write(
+            &path,
+            "date,player,difficulty,min,max,attempts,seconds,won\nyesterday,alice,easy,1,50,many,3,true\n",
+        )
+        .unwrap();
+
+        let error = load(&path).unwrap_err().to_string();
+        fs::remove_file(&path).unwrap();
+
+        assert!(
+            error.ends_with("line 2: bad attempts \"many\""),
+            "{}",
+            error
+        );
+    }
+}
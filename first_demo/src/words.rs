@@ -0,0 +1,421 @@
+// words: guess a five-letter word in six tries.
+//
+//     first_demo words              # a random word
+//     first_demo words --daily      # today's word, the same for everyone
+//     first_demo words --seed=42    # the same word every time
+//
+// The word comes from words.txt, which is built into the program. A guess can be any five
+// letters. Every letter of a guess is marked
+//
+// exact    [x]  the word has it in this place
+// present  (x)  the word has it, but somewhere else
+// absent    x   the word doesn't have it, or not this many times
+//
+// A letter guessed more often than the word has it is marked for as many as the word has,
+// exact places first and then from the left: guessing "eerie" for "there" marks the last e
+// exact, the first present and the second absent.
+
+use crate::game::State;
+use crate::locale::{Catalogue, Language};
+use chrono::{Datelike, NaiveDate};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub const LENGTH: usize = 5;
+pub const TRIES: usize = 6;
+
+pub const WORDS: &str = include_str!("words.txt");
+
+pub fn words() -> Vec<&'static str> {
+    WORDS.lines().collect()
+}
+
+// LENGTH lowercase ASCII letters, the only words Evaluation compares
+pub fn is_word(text: &str) -> bool {
+    text.len() == LENGTH && text.bytes().all(|letter| letter.is_ascii_lowercase())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mark {
+    Exact,
+    Present,
+    Absent,
+}
+
+// How a guess compares to the word, letter by letter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub guess: String,
+    pub marks: [Mark; LENGTH],
+}
+
+impl Evaluation {
+    // None unless guess and answer are both words
+    pub fn of(guess: &str, answer: &str) -> Option<Evaluation> {
+        if !is_word(guess) || !is_word(answer) {
+            return None;
+        }
+
+        let guess_letters = guess.as_bytes();
+        let answer_letters = answer.as_bytes();
+        let mut marks = [Mark::Absent; LENGTH];
+        // letters of the answer not matched yet, by letter
+        let mut unmatched = [0u8; 26];
+
+        for index in 0..LENGTH {
+            if guess_letters[index] == answer_letters[index] {
+                marks[index] = Mark::Exact;
+            } else {
+                unmatched[(answer_letters[index] - b'a') as usize] += 1;
+            }
+        }
+
+        for index in 0..LENGTH {
+            let left = &mut unmatched[(guess_letters[index] - b'a') as usize];
+            if marks[index] != Mark::Exact && *left > 0 {
+                marks[index] = Mark::Present;
+                *left -= 1;
+            }
+        }
+
+        Some(Evaluation {
+            guess: guess.to_string(),
+            marks,
+        })
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.marks.iter().all(|&mark| mark == Mark::Exact)
+    }
+}
+
+impl fmt::Display for Evaluation {
+    // [c](r) a  n [e]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells: Vec<String> = self
+            .guess
+            .chars()
+            .zip(self.marks.iter())
+            .map(|(letter, mark)| match mark {
+                Mark::Exact => format!("[{}]", letter),
+                Mark::Present => format!("({})", letter),
+                Mark::Absent => format!(" {} ", letter),
+            })
+            .collect();
+        write!(f, "{}", cells.join(""))
+    }
+}
+
+// A guess as typed, or why it can't be one
+pub fn parse_guess(text: &str, messages: &Catalogue) -> Result<String, String> {
+    let word = text.trim().to_lowercase();
+
+    if !word.chars().all(|letter| letter.is_ascii_lowercase()) {
+        let text = format!("{:?}", text.trim());
+        return Err(messages.text("words.not-letters", &[("text", &text)]));
+    }
+    if word.len() != LENGTH {
+        return Err(messages.text(
+            "words.length",
+            &[("length", &LENGTH), ("letters", &word.len())],
+        ));
+    }
+    Ok(word)
+}
+
+// The seed random picks the word for date with
+pub fn daily_seed(date: NaiveDate) -> u64 {
+    date.num_days_from_ce() as u64
+}
+
+pub struct WordGame {
+    answer: String,
+    evaluations: Vec<Evaluation>,
+    state: State,
+    messages: Catalogue,
+}
+
+impl WordGame {
+    // None unless answer is a word
+    pub fn new(answer: &str) -> Option<WordGame> {
+        if !is_word(answer) {
+            return None;
+        }
+
+        Some(WordGame {
+            answer: answer.to_string(),
+            evaluations: Vec::new(),
+            state: State::Playing,
+            messages: Catalogue::default(),
+        })
+    }
+
+    // The same game, played in language rather than English
+    pub fn in_language(self, language: Language) -> WordGame {
+        WordGame {
+            messages: Catalogue::new(language),
+            ..self
+        }
+    }
+
+    // A word from the list, picked the way Game picks its number
+    pub fn random(seed: u64) -> WordGame {
+        let words = words();
+        let index = StdRng::seed_from_u64(seed).gen_range(0, words.len());
+        WordGame::new(words[index]).expect("words.txt has only words")
+    }
+
+    // The word for date: everyone playing on the same day gets the same one
+    pub fn daily(date: NaiveDate) -> WordGame {
+        WordGame::random(daily_seed(date))
+    }
+
+    pub fn answer(&self) -> &str {
+        &self.answer
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn evaluations(&self) -> &[Evaluation] {
+        &self.evaluations
+    }
+
+    pub fn guess(&mut self, text: &str) -> Result<&Evaluation, String> {
+        assert_eq!(State::Playing, self.state, "the game is over");
+
+        let word = parse_guess(text, &self.messages)?;
+        if self.evaluations.iter().any(|before| before.guess == word) {
+            return Err(self.messages.text("words.repeated", &[("word", &word)]));
+        }
+
+        let evaluation =
+            Evaluation::of(&word, &self.answer).expect("parse_guess and new only let words in");
+        if evaluation.is_solved() {
+            self.state = State::Won;
+        } else if self.evaluations.len() + 1 == TRIES {
+            self.state = State::Lost;
+        }
+        self.evaluations.push(evaluation);
+
+        Ok(self.evaluations.last().unwrap())
+    }
+
+    // Plays until the word is found, the tries run out or input does
+    pub fn play<R: BufRead, W: Write>(&mut self, mut input: R, out: &mut W) -> io::Result<State> {
+        let messages = self.messages;
+        let answer = self.answer.clone();
+        let telling_word = |key: &str| messages.text(key, &[("word", &answer)]);
+
+        writeln!(
+            out,
+            "{}",
+            messages.text("words.intro", &[("length", &LENGTH), ("tries", &TRIES)])
+        )?;
+        writeln!(out, "{}", messages.text("words.legend", &[]))?;
+
+        while self.state == State::Playing {
+            let number = self.evaluations.len() + 1;
+            write!(
+                out,
+                "{}",
+                messages.text("words.prompt", &[("number", &number), ("tries", &TRIES)])
+            )?;
+            out.flush()?;
+
+            let mut line = String::new();
+            let read = input.read_line(&mut line)?;
+            if read == 0 || line.trim() == "quit" {
+                // without input there was no newline after the prompt
+                if read == 0 {
+                    writeln!(out)?;
+                }
+                self.state = State::Quit;
+                writeln!(out, "{}", telling_word("words.bye"))?;
+                break;
+            }
+
+            match self.guess(&line) {
+                Ok(evaluation) => writeln!(out, "{}", evaluation)?,
+                Err(why) => writeln!(out, "{}", why)?,
+            }
+        }
+
+        match self.state {
+            State::Won => {
+                let tries = self.evaluations.len() as u64;
+                writeln!(out, "{}", messages.plural("words.won", tries, &[]))?
+            }
+            State::Lost => writeln!(out, "{}", telling_word("words.lost"))?,
+            _ => {}
+        }
+        Ok(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // E, P and A for exact, present and absent
+    fn marks(pattern: &str) -> [Mark; LENGTH] {
+        let mut marks = [Mark::Absent; LENGTH];
+        for (mark, letter) in marks.iter_mut().zip(pattern.chars()) {
+            *mark = match letter {
+                'E' => Mark::Exact,
+                'P' => Mark::Present,
+                _ => Mark::Absent,
+            };
+        }
+        marks
+    }
+
+    fn check(guess: &str, answer: &str, pattern: &str) {
+        assert_eq!(
+            marks(pattern),
+            Evaluation::of(guess, answer).unwrap().marks,
+            "{} for {}",
+            guess,
+            answer
+        );
+    }
+
+    #[test]
+    fn distinct_letters() {
+        check("crane", "crane", "EEEEE");
+        check("ghost", "crane", "AAAAA");
+        check("nacre", "crane", "PPPPE");
+        check("trace", "crane", "AEEPE");
+    }
+
+    #[test]
+    fn letter_guessed_more_often_than_in_the_word() {
+        // one e in abide: the first e guessed is present, the second absent
+        check("speed", "abide", "AAPAP");
+        // the exact e is matched first, then the rest from the left
+        check("eerie", "there", "PAPAE");
+        check("geese", "those", "AAAEE");
+    }
+
+    #[test]
+    fn letter_in_the_word_more_often_than_guessed() {
+        check("llama", "hello", "PPAAA");
+        check("lolly", "hello", "APEEA");
+        check("hello", "lolly", "AAEEP");
+        check("eerie", "eerie", "EEEEE");
+    }
+
+    #[test]
+    fn exact_wins_over_present() {
+        // the a in place takes the only a, even though an earlier a comes first
+        check("aloha", "pizza", "AAAAE");
+        check("aabbb", "xxaxx", "PAAAA");
+        check("bbbaa", "xxxxa", "AAAAE");
+    }
+
+    #[test]
+    fn shown_with_brackets() {
+        assert_eq!(
+            "(e) e (r) i [e]",
+            Evaluation::of("eerie", "there").unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn only_words_are_compared() {
+        for not_a_word in [
+            "CRANE", "Crane", "cr4ne", "cr ne", "crâne", "cran", "cranes", "",
+        ] {
+            assert_eq!(
+                None,
+                Evaluation::of(not_a_word, "crane"),
+                "{:?}",
+                not_a_word
+            );
+            assert_eq!(
+                None,
+                Evaluation::of("crane", not_a_word),
+                "{:?}",
+                not_a_word
+            );
+            assert!(WordGame::new(not_a_word).is_none(), "{:?}", not_a_word);
+        }
+    }
+
+    #[test]
+    fn guesses_are_checked() {
+        let english = Catalogue::default();
+        assert_eq!(Ok(String::from("crane")), parse_guess(" CRANE\n", &english));
+        assert!(parse_guess("cran", &english).is_err());
+        assert!(parse_guess("cranes", &english).is_err());
+        assert!(parse_guess("cr4ne", &english).is_err());
+        assert!(parse_guess("crâne", &english).is_err());
+    }
+
+    #[test]
+    fn word_list() {
+        let words = words();
+        assert!(words.len() > 100);
+        assert!(words
+            .iter()
+            .all(|word| word.len() == LENGTH && word.chars().all(|c| c.is_ascii_lowercase())));
+
+        let mut sorted = words.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(words.len(), sorted.len(), "words.txt has duplicates");
+    }
+
+    #[test]
+    fn daily_word_depends_only_on_the_date() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        assert_eq!(WordGame::daily(day).answer(), WordGame::daily(day).answer());
+        let week: Vec<String> = (0..7)
+            .map(|offset| {
+                let date = day + chrono::Duration::days(offset);
+                WordGame::daily(date).answer().to_string()
+            })
+            .collect();
+        assert!(week.iter().any(|word| word != &week[0]));
+    }
+
+    #[test]
+    fn winning_session() {
+        let mut game = WordGame::new("there").unwrap();
+        let mut out = Vec::new();
+        let state = game
+            .play("eerie\nthe\neerie\nthere\n".as_bytes(), &mut out)
+            .unwrap();
+
+        assert_eq!(State::Won, state);
+        assert_eq!(
+            "\
+Guess the 5-letter word in 6 tries, or type quit.
+[x] is in the right place, (x) is in the word elsewhere.
+Guess 1 of 6: (e) e (r) i [e]
+Guess 2 of 6: Guess a word of 5 letters, not 3.
+Guess 2 of 6: You already guessed eerie.
+Guess 2 of 6: [t][h][e][r][e]
+You win in 2!
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn six_tries() {
+        let mut game = WordGame::new("there").unwrap();
+        let mut out = Vec::new();
+        let input = "aaaaa\nbbbbb\nccccc\nddddd\nfffff\nggggg\nthere\n";
+
+        assert_eq!(State::Lost, game.play(input.as_bytes(), &mut out).unwrap());
+        assert_eq!(TRIES, game.evaluations().len());
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .ends_with("Out of tries! The word was there.\n"));
+    }
+}
@@ -0,0 +1,31 @@
+// Every session in tests/sessions is a golden test: replaying it has to print exactly
+// what was recorded. Record a new one with
+//
+//     first_demo --seed=1 --record tests/sessions/name.log
+//
+// and when output changes on purpose, record the ones that differ again.
+
+use first_demo::Session;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn recorded_sessions_replay_the_same() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sessions");
+    let mut replayed = 0;
+
+    for entry in fs::read_dir(&directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "log") {
+            continue;
+        }
+
+        let session = Session::load(&path).unwrap();
+        if let Err(e) = session.check() {
+            panic!("{}: {}", path.display(), e);
+        }
+        replayed += 1;
+    }
+
+    assert!(replayed > 0, "no sessions in {}", directory.display());
+}
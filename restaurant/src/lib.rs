@@ -1,4 +1,7 @@
 
+// The menu lives in src/menu.rs; pub so other crates can load and search it.
+pub mod menu;
+
 mod front_of_house {
     // Moodule must be pub for eat_at_restaurant to call
     pub mod hosting {
@@ -0,0 +1,587 @@
+// The menu: what the kitchen can make, what it costs and when it is served.
+//
+// A menu is loaded from a TOML or a JSON file, like menu.toml next to Cargo.toml:
+//
+//     [windows]
+//     breakfast = { from = "07:00", until = "11:00" }
+//
+//     [[items]]
+//     id = "pancakes"
+//     name = "Pancakes"
+//     category = "main"
+//     price_cents = 850
+//     allergens = ["gluten", "eggs", "dairy"]
+//     available = ["breakfast"]
+//
+// Prices are whole cents, so adding them up never rounds. An item without available is
+// served whenever the restaurant is open; otherwise only in the windows it names, which
+// the menu itself defines. A window that ends before it starts runs past midnight.
+//
+// Loading checks the whole menu and reports every problem at once, rather than the first.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Appetizer,
+    Main,
+    Side,
+    Dessert,
+    Drink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Allergen {
+    Gluten,
+    Dairy,
+    Eggs,
+    Nuts,
+    Peanuts,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+    Celery,
+    Mustard,
+}
+
+// A time of day to the minute, written 07:30
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Time {
+    minutes: u16,
+}
+
+impl Time {
+    pub fn new(hour: u8, minute: u8) -> Option<Time> {
+        if hour < 24 && minute < 60 {
+            Some(Time {
+                minutes: hour as u16 * 60 + minute as u16,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Time, String> {
+        let bad = || format!("{:?} is not a time like 07:30", text);
+
+        let (hour, minute) = text.split_once(':').ok_or_else(bad)?;
+        if hour.len() != 2 || minute.len() != 2 {
+            return Err(bad());
+        }
+        let hour = hour.parse().map_err(|_| bad())?;
+        let minute = minute.parse().map_err(|_| bad())?;
+        Time::new(hour, minute).ok_or_else(bad)
+    }
+}
+
+impl TryFrom<String> for Time {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Time, String> {
+        Time::parse(&text)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.minutes / 60, self.minutes % 60)
+    }
+}
+
+// From one time up to, but not including, another
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Window {
+    pub from: Time,
+    pub until: Time,
+}
+
+impl Window {
+    pub fn contains(&self, time: Time) -> bool {
+        if self.from <= self.until {
+            self.from <= time && time < self.until
+        } else {
+            // past midnight: 22:00 to 02:00 is 22:00 to the end of the day and on to 02:00
+            self.from <= time || time < self.until
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Item {
+    pub id: String,
+    pub name: String,
+    pub category: Category,
+    // signed, so a negative price in a file can be reported rather than failing to parse
+    pub price_cents: i64,
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+    // names of the menu's windows; none means any time
+    #[serde(default)]
+    pub available: Vec<String>,
+}
+
+// Something wrong with a menu
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    DuplicateId(String),
+    NegativePrice { id: String, price_cents: i64 },
+    UnknownWindow { id: String, window: String },
+    EmptyWindow(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::DuplicateId(id) => write!(f, "more than one item has the id {}", id),
+            Problem::NegativePrice { id, price_cents } => {
+                write!(f, "{} has a negative price of {} cents", id, price_cents)
+            }
+            Problem::UnknownWindow { id, window } => {
+                write!(f, "{} is available in {}, which isn't a window", id, window)
+            }
+            Problem::EmptyWindow(name) => write!(f, "the window {} starts when it ends", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MenuError {
+    Io(PathBuf, io::Error),
+    // the file isn't TOML or JSON, or not a menu
+    Parse(String),
+    UnknownFormat(PathBuf),
+    Invalid(Vec<Problem>),
+}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MenuError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            MenuError::Parse(why) => write!(f, "not a menu: {}", why),
+            MenuError::UnknownFormat(path) => {
+                write!(f, "{}: a menu is a .toml or a .json file", path.display())
+            }
+            MenuError::Invalid(problems) => {
+                let problems: Vec<String> = problems.iter().map(Problem::to_string).collect();
+                write!(f, "{}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl Error for MenuError {}
+
+// What a menu file holds
+#[derive(Deserialize)]
+struct MenuFile {
+    #[serde(default)]
+    windows: HashMap<String, Window>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+pub struct Menu {
+    windows: HashMap<String, Window>,
+    items: Vec<Item>,
+    // where each id is in items
+    by_id: HashMap<String, usize>,
+}
+
+impl Menu {
+    pub fn new(windows: HashMap<String, Window>, items: Vec<Item>) -> Result<Menu, MenuError> {
+        let problems = validate(&windows, &items);
+        if !problems.is_empty() {
+            return Err(MenuError::Invalid(problems));
+        }
+
+        let by_id = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (item.id.clone(), index))
+            .collect();
+        Ok(Menu {
+            windows,
+            items,
+            by_id,
+        })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Menu, MenuError> {
+        let file: MenuFile = toml::from_str(text).map_err(|e| MenuError::Parse(e.to_string()))?;
+        Menu::new(file.windows, file.items)
+    }
+
+    pub fn from_json(text: &str) -> Result<Menu, MenuError> {
+        let file: MenuFile =
+            serde_json::from_str(text).map_err(|e| MenuError::Parse(e.to_string()))?;
+        Menu::new(file.windows, file.items)
+    }
+
+    // A .toml or .json file
+    pub fn load(path: &Path) -> Result<Menu, MenuError> {
+        let text = |path: &Path| {
+            fs::read_to_string(path).map_err(|e| MenuError::Io(path.to_path_buf(), e))
+        };
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Menu::from_toml(&text(path)?),
+            Some("json") => Menu::from_json(&text(path)?),
+            _ => Err(MenuError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.by_id.get(id).map(|&index| &self.items[index])
+    }
+
+    pub fn window(&self, name: &str) -> Option<&Window> {
+        self.windows.get(name)
+    }
+
+    // item can come from anywhere, so a window this menu doesn't have is never open
+    pub fn is_available(&self, item: &Item, time: Time) -> bool {
+        item.available.is_empty()
+            || item
+                .available
+                .iter()
+                .any(|name| self.windows.get(name).is_some_and(|w| w.contains(time)))
+    }
+
+    // The items that pass filter, in menu order
+    pub fn filter(&self, filter: &Filter) -> Vec<&Item> {
+        self.items
+            .iter()
+            .filter(|item| {
+                filter
+                    .category
+                    .is_none_or(|category| item.category == category)
+            })
+            .filter(|item| filter.at.is_none_or(|time| self.is_available(item, time)))
+            .filter(|item| {
+                filter
+                    .max_price_cents
+                    .is_none_or(|price| item.price_cents <= price)
+            })
+            .filter(|item| {
+                !item
+                    .allergens
+                    .iter()
+                    .any(|allergen| filter.free_of.contains(allergen))
+            })
+            .collect()
+    }
+}
+
+// Everything wrong with windows and items, in the order the items come
+pub fn validate(windows: &HashMap<String, Window>, items: &[Item]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    let mut names: Vec<&String> = windows.keys().collect();
+    names.sort();
+    for name in names {
+        if windows[name].from == windows[name].until {
+            problems.push(Problem::EmptyWindow(name.clone()));
+        }
+    }
+
+    let mut seen = HashMap::new();
+    for item in items {
+        // each id once, however often it comes
+        let count = seen.entry(item.id.as_str()).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            problems.push(Problem::DuplicateId(item.id.clone()));
+        }
+
+        if item.price_cents < 0 {
+            problems.push(Problem::NegativePrice {
+                id: item.id.clone(),
+                price_cents: item.price_cents,
+            });
+        }
+        for window in &item.available {
+            if !windows.contains_key(window) {
+                problems.push(Problem::UnknownWindow {
+                    id: item.id.clone(),
+                    window: window.clone(),
+                });
+            }
+        }
+    }
+
+    problems
+}
+
+// Which items to list: Filter::new().category(Category::Main).at(noon).free_of(Allergen::Nuts)
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    category: Option<Category>,
+    at: Option<Time>,
+    max_price_cents: Option<i64>,
+    free_of: Vec<Allergen>,
+}
+
+impl Filter {
+    pub fn new() -> Filter {
+        Filter::default()
+    }
+
+    pub fn category(mut self, category: Category) -> Filter {
+        self.category = Some(category);
+        self
+    }
+
+    // Served at time
+    pub fn at(mut self, time: Time) -> Filter {
+        self.at = Some(time);
+        self
+    }
+
+    pub fn max_price_cents(mut self, price_cents: i64) -> Filter {
+        self.max_price_cents = Some(price_cents);
+        self
+    }
+
+    // Without allergen, on top of any left out before
+    pub fn free_of(mut self, allergen: Allergen) -> Filter {
+        self.free_of.push(allergen);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MENU: &str = include_str!("../menu.toml");
+
+    fn time(text: &str) -> Time {
+        Time::parse(text).unwrap()
+    }
+
+    fn ids(items: Vec<&Item>) -> Vec<&str> {
+        items.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    #[test]
+    fn loads_the_example_menu() {
+        let menu = Menu::from_toml(MENU).unwrap();
+
+        let pancakes = menu.get("pancakes").unwrap();
+        assert_eq!("Pancakes", pancakes.name);
+        assert_eq!(Category::Main, pancakes.category);
+        assert_eq!(850, pancakes.price_cents);
+        assert!(pancakes.allergens.contains(&Allergen::Gluten));
+        assert!(menu.get("waffles").is_none());
+    }
+
+    #[test]
+    fn json_menus_too() {
+        let menu = Menu::from_json(
+            r#"{
+                "windows": { "lunch": { "from": "11:30", "until": "14:30" } },
+                "items": [
+                    { "id": "soup", "name": "Soup of the day", "category": "appetizer",
+                      "price_cents": 650, "allergens": ["celery"], "available": ["lunch"] },
+                    { "id": "water", "name": "Water", "category": "drink", "price_cents": 0 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, menu.items().len());
+        assert_eq!(vec![Allergen::Celery], menu.get("soup").unwrap().allergens);
+        assert!(menu.get("water").unwrap().available.is_empty());
+    }
+
+    #[test]
+    fn load_picks_the_format_from_the_extension() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("menu.toml");
+        assert_eq!(
+            Menu::from_toml(MENU).unwrap().items(),
+            Menu::load(&path).unwrap().items()
+        );
+
+        assert!(matches!(
+            Menu::load(Path::new("menu.yaml")),
+            Err(MenuError::UnknownFormat(_))
+        ));
+        assert!(matches!(
+            Menu::load(Path::new("no-such-menu.json")),
+            Err(MenuError::Io(_, _))
+        ));
+    }
+
+    #[test]
+    fn filters() {
+        let menu = Menu::from_toml(MENU).unwrap();
+
+        assert_eq!(
+            vec!["lemonade", "coffee"],
+            ids(menu.filter(&Filter::new().category(Category::Drink)))
+        );
+        // breakfast is over at 11:00, and the fries only come later
+        let at_eleven = ids(menu.filter(&Filter::new().at(time("11:00"))));
+        assert!(!at_eleven.contains(&"pancakes"));
+        assert!(!at_eleven.contains(&"late-fries"));
+        assert!(at_eleven.contains(&"coffee"));
+
+        assert_eq!(
+            vec!["lemonade", "coffee"],
+            ids(menu.filter(&Filter::new().max_price_cents(400)))
+        );
+        let safe = ids(menu.filter(
+            &Filter::new()
+                .free_of(Allergen::Gluten)
+                .free_of(Allergen::Dairy),
+        ));
+        assert!(!safe.contains(&"pancakes"));
+        assert!(!safe.contains(&"cheesecake"));
+        assert!(safe.contains(&"lemonade"));
+
+        let breakfast = Filter::new().category(Category::Main).at(time("08:15"));
+        assert_eq!(
+            vec!["pancakes", "eggs-benedict"],
+            ids(menu.filter(&breakfast))
+        );
+    }
+
+    #[test]
+    fn windows_past_midnight() {
+        let late = Window {
+            from: time("22:00"),
+            until: time("02:00"),
+        };
+
+        assert!(late.contains(time("23:59")));
+        assert!(late.contains(time("00:00")));
+        assert!(late.contains(time("01:59")));
+        assert!(!late.contains(time("02:00")));
+        assert!(!late.contains(time("12:00")));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!("07:05", time("07:05").to_string());
+        assert_eq!(Time::new(23, 59), Some(time("23:59")));
+        for bad in &["7:05", "24:00", "12:60", "noon", "12:5", "-1:00"] {
+            assert!(Time::parse(bad).is_err(), "{}", bad);
+        }
+        // in a file too
+        assert!(matches!(
+            Menu::from_toml("[windows]\nlunch = { from = \"12\", until = \"14:00\" }\n"),
+            Err(MenuError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let text = r#"
+            [windows]
+            never = { from = "10:00", until = "10:00" }
+
+            [[items]]
+            id = "soup"
+            name = "Soup"
+            category = "appetizer"
+            price_cents = 650
+
+            [[items]]
+            id = "soup"
+            name = "Other soup"
+            category = "appetizer"
+            price_cents = -100
+            available = ["brunch"]
+
+            [[items]]
+            id = "soup"
+            name = "Third soup"
+            category = "appetizer"
+            price_cents = 700
+        "#;
+
+        let problems = match Menu::from_toml(text) {
+            Err(MenuError::Invalid(problems)) => problems,
+            other => panic!("{:?}", other.map(|menu| menu.items().len())),
+        };
+        assert_eq!(
+            vec![
+                Problem::EmptyWindow(String::from("never")),
+                Problem::DuplicateId(String::from("soup")),
+                Problem::NegativePrice {
+                    id: String::from("soup"),
+                    price_cents: -100
+                },
+                Problem::UnknownWindow {
+                    id: String::from("soup"),
+                    window: String::from("brunch")
+                },
+            ],
+            problems
+        );
+        assert_eq!(
+            "the window never starts when it ends; more than one item has the id soup; \
+             soup has a negative price of -100 cents; \
+             soup is available in brunch, which isn't a window",
+            MenuError::Invalid(problems).to_string()
+        );
+    }
+
+    #[test]
+    fn items_from_elsewhere_with_unknown_windows() {
+        let menu = Menu::from_toml(MENU).unwrap();
+        let special = Item {
+            id: String::from("special"),
+            name: String::from("Chef's special"),
+            category: Category::Main,
+            price_cents: 1500,
+            allergens: Vec::new(),
+            available: vec![String::from("brunch"), String::from("dinner")],
+        };
+        let brunch_only = Item {
+            available: vec![String::from("brunch")],
+            ..special.clone()
+        };
+
+        assert!(menu.is_available(&special, time("19:00")));
+        assert!(!menu.is_available(&brunch_only, time("19:00")));
+        assert!(!menu.is_available(&brunch_only, time("10:30")));
+    }
+
+    #[test]
+    fn unknown_categories_and_allergens_are_parse_errors() {
+        let item = |field: &str| {
+            format!(
+                "[[items]]\nid = \"x\"\nname = \"X\"\nprice_cents = 1\n{}\n",
+                field
+            )
+        };
+
+        assert!(Menu::from_toml(&item("category = \"main\"")).is_ok());
+        assert!(matches!(
+            Menu::from_toml(&item("category = \"brunch\"")),
+            Err(MenuError::Parse(_))
+        ));
+        assert!(matches!(
+            Menu::from_toml(&item(
+                "category = \"main\"\nallergens = [\"gluten\", \"love\"]"
+            )),
+            Err(MenuError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+// The search behind minigrep and minigrep2, for any program that wants to grep
+// without shelling out.
+//
+//     let searcher = SearcherBuilder::new().ignore_case(true).build("rust");
+//     for found in searcher.matches(contents) {
+//         println!("{}: {}", found.line_number, found.line);
+//     }
+//
+// SearcherBuilder  how to compare: case, diacritics, -v, a plain string or a Query
+// Searcher         runs the search; matches() is an iterator of Match, search() feeds a Sink
+// Sink             receives the selected lines one at a time and can stop the search early
+//
+// search and search_case_insensitive are the small functions the two tools started out with.
+
+mod literal;
+mod normalize;
+mod query;
+mod searcher;
+mod sink;
+
+pub use crate::literal::{find_lines, Lines};
+pub use crate::normalize::Folding;
+pub use crate::query::{Query, Scope};
+pub use crate::searcher::{Matches, Searcher, SearcherBuilder};
+pub use crate::sink::{Count, Print, Sink};
+
+// A line selected by a Searcher.
+// byte_offset is where the match starts in the searched text, counted in the original, unnormalized bytes.
+// Lines selected with invert_match contain no match, so for them it is where the line starts.
+// With Scope::Paragraph, line is the whole paragraph and line_number the number of its first line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match<'a> {
+    pub line_number: usize,
+    pub byte_offset: usize,
+    pub line: &'a str,
+}
+
+// lifetime 'a used with the contents argument and the return value.
+// the lifetime parameters specify which argument lifetime is connected to the lifetime of the return value.
+// In this case, we indicate that the returned vector should contain string slices that reference slices of the argument contents (rather than the argument query).
+// n other words, we tell Rust that the data returned by the search function will live as long as the data passed into the search function in the contents argument.
+// CASE_INSENSITIVE=1 cargo run to poem.txt
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    /*
+    Version 1:
+    let mut results = Vec::new();
+
+    for line in contents.lines() {
+        if line.contains(query) {
+            results.push(line);
+        }
+    }
+
+    results
+    */
+
+    // Using iterator adaptor methods in the implementation of the search function
+    contents
+        .lines()
+        .filter(|line| line.contains(query))
+        .collect()
+}
+
+// CASE_INSENSITIVE=1 cargo run to poem.txt
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    /*
+    Version 1:
+    let query = query.to_lowercase();
+    let mut results = Vec::new();
+
+    for line in contents.lines() {
+        if line.to_lowercase().contains(&query) {
+            results.push(line);
+        }
+    }
+
+    results
+    */
+
+    // Using iterator adaptor methods in the implementation of the search function
+    let query = query.to_lowercase();
+    contents
+        .lines()
+        .filter(|line| line.to_lowercase().contains(&query))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_sensitive() {
+        let query = "duct";
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.";
+
+        assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let query = "rUsT";
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            search_case_insensitive(query, contents)
+        );
+    }
+}
@@ -0,0 +1,267 @@
+// Searching for a literal string quickly.
+//
+// find_lines doesn't split the text into lines up front. It jumps from one occurrence
+// of the query's first byte to the next with memchr (which compares 16 or 32 bytes at a
+// time), checks whether the whole query follows, and only then looks for the newlines on
+// either side to cut out the line. Line numbers are counted the same way, with memchr
+// over the stretch between two hits.
+//
+// Folding leaves ASCII text as it is, apart from lowercasing it for a case-insensitive
+// search, so for a query that is ASCII once folded the Searcher uses find_folded_lines:
+// ASCII lines are compared byte by byte, ignoring ASCII case if the folding does, and
+// the scan stops at every character outside ASCII too, to fold and compare just the line
+// it is in. A file that is mostly ASCII is then hardly folded at all. Queries with other
+// characters, and -v, which has to look at every line, fold line by line.
+
+use crate::{Folding, Match};
+use memchr::{memchr, memchr2, memchr_iter, memrchr};
+
+// Every line containing query, lazily, with the same line numbers and offsets as the line-by-line search.
+// The query can't contain a line break; a line never contains one.
+pub fn find_lines<'q, 'a>(query: &'q str, contents: &'a str) -> Lines<'q, 'a> {
+    Lines {
+        query,
+        folding: None,
+        contents,
+        position: 0,
+        hit: Next::default(),
+        non_ascii: Next::default(),
+        line_number: 1,
+        counted_to: 0,
+    }
+}
+
+// The lines whose folded text contains folded_query, which has to be ASCII and already
+// folded with folding. Same lines, numbers and offsets as folding every line.
+pub(crate) fn find_folded_lines<'q, 'a>(
+    folded_query: &'q str,
+    folding: Folding,
+    contents: &'a str,
+) -> Lines<'q, 'a> {
+    Lines {
+        folding: Some(folding),
+        ..find_lines(folded_query, contents)
+    }
+}
+
+pub struct Lines<'q, 'a> {
+    query: &'q str,
+    // None: compare bytes everywhere, as find_lines does
+    folding: Option<Folding>,
+    contents: &'a str,
+    // where to look for the next hit; always the start of a line
+    position: usize,
+    hit: Next,
+    // only looked for with a folding
+    non_ascii: Next,
+    // the line number of counted_to
+    line_number: usize,
+    counted_to: usize,
+}
+
+// Where the next one of something is, looked for again only once the search has passed it
+#[derive(Default)]
+struct Next {
+    at: Option<usize>,
+    looked: bool,
+}
+
+impl Next {
+    fn at_or_after<F: FnOnce(usize) -> Option<usize>>(
+        &mut self,
+        position: usize,
+        find: F,
+    ) -> Option<usize> {
+        if !self.looked || self.at.is_some_and(|at| at < position) {
+            self.at = find(position);
+            self.looked = true;
+        }
+        self.at
+    }
+}
+
+// Offset of the first occurrence of an ASCII query in haystack at or after from.
+// With ignore_case the query has to be lowercase.
+pub(crate) fn find_ascii(
+    query: &[u8],
+    haystack: &[u8],
+    ignore_case: bool,
+    from: usize,
+) -> Option<usize> {
+    if from >= haystack.len() {
+        return None;
+    }
+
+    let (&first, rest) = match query.split_first() {
+        Some(split) => split,
+        // Like str::contains, an empty query is in every line
+        None => return Some(from),
+    };
+
+    let mut from = from;
+    loop {
+        let found = if ignore_case {
+            memchr2(first, first.to_ascii_uppercase(), &haystack[from..])
+        } else {
+            memchr(first, &haystack[from..])
+        }?;
+        let at = from + found;
+        let after = &haystack[at + 1..];
+        let matched = if ignore_case {
+            after
+                .get(..rest.len())
+                .is_some_and(|next| next.eq_ignore_ascii_case(rest))
+        } else {
+            after.starts_with(rest)
+        };
+        if matched {
+            return Some(at);
+        }
+        from = at + 1;
+    }
+}
+
+// Offset of the first byte at or after from that isn't ASCII. is_ascii checks a word at a time.
+fn find_non_ascii(bytes: &[u8], from: usize) -> Option<usize> {
+    const BLOCK: usize = 4096;
+
+    let mut start = from;
+    while start < bytes.len() {
+        let block = &bytes[start..(start + BLOCK).min(bytes.len())];
+        if !block.is_ascii() {
+            return block
+                .iter()
+                .position(|byte| !byte.is_ascii())
+                .map(|at| start + at);
+        }
+        start += block.len();
+    }
+    None
+}
+
+impl<'a> Iterator for Lines<'_, 'a> {
+    type Item = Match<'a>;
+
+    fn next(&mut self) -> Option<Match<'a>> {
+        let bytes = self.contents.as_bytes();
+        let query = self.query.as_bytes();
+        let ignore_case = self.folding.is_some_and(|folding| folding.ignore_case);
+
+        loop {
+            let position = self.position;
+            let hit = self
+                .hit
+                .at_or_after(position, |from| find_ascii(query, bytes, ignore_case, from));
+            let non_ascii = match self.folding {
+                Some(_) => self
+                    .non_ascii
+                    .at_or_after(position, |from| find_non_ascii(bytes, from)),
+                None => None,
+            };
+            let candidate = hit.into_iter().chain(non_ascii).min()?;
+
+            let line_start = memrchr(b'\n', &bytes[..candidate]).map_or(0, |newline| newline + 1);
+            let line_end = memchr(b'\n', &bytes[candidate..])
+                .map_or(bytes.len(), |newline| candidate + newline);
+            // One Match per line, however many hits it has
+            self.position = line_end + 1;
+
+            // str::lines drops the \r of a \r\n too
+            let line = &self.contents[line_start..line_end];
+            let line = line.strip_suffix('\r').unwrap_or(line);
+
+            // An ASCII line was only stopped at for a hit, which is its first one
+            let byte_offset = match self.folding {
+                Some(folding) if !line.is_ascii() => match folding.find(self.query, line) {
+                    Some(found) => line_start + found.start,
+                    None => continue,
+                },
+                _ => candidate,
+            };
+
+            self.line_number += memchr_iter(b'\n', &bytes[self.counted_to..line_start]).count();
+            self.counted_to = line_start;
+            return Some(Match {
+                line_number: self.line_number,
+                byte_offset,
+                line,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearcherBuilder;
+
+    #[test]
+    fn same_lines_as_search_with() {
+        let contents = "\
+Rust:\r
+safe, fast, productive.
+Pick three. fast fast
+Duct tape.
+
+fast";
+
+        for query in ["fast", "Rust", "t", "", "nowhere", "three. fast"] {
+            assert_eq!(
+                SearcherBuilder::new()
+                    .build(query)
+                    .slow_matches(contents)
+                    .collect::<Vec<Match>>(),
+                find_lines(query, contents).collect::<Vec<Match>>(),
+                "query {:?}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn folded_lines_as_search_with() {
+        let contents = "\
+Caf\u{e9} au lait, FAST
+safe, fast, productive.\r
+na\u{ef}ve fas\u{0074}
+\u{212a}elvin and K\u{fc}che
+
+cafe\u{301}: fast";
+
+        for (ignore_case, ignore_diacritics) in
+            [(false, false), (true, false), (true, true), (false, true)]
+        {
+            let builder = SearcherBuilder::new()
+                .ignore_case(ignore_case)
+                .ignore_diacritics(ignore_diacritics);
+            for query in ["fast", "FAST", "cafe", "k", "e", "", "nowhere", "t, p"] {
+                let searcher = builder.build(query);
+                let folded = builder.folding().fold(query);
+                assert_eq!(
+                    searcher.slow_matches(contents).collect::<Vec<Match>>(),
+                    find_folded_lines(&folded, builder.folding(), contents).collect::<Vec<Match>>(),
+                    "query {:?}, ignore case {}, ignore diacritics {}",
+                    query,
+                    ignore_case,
+                    ignore_diacritics
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn candidates_that_dont_match_are_skipped() {
+        let contents = "fas fas\nfasfast\n";
+
+        let found: Vec<Match> = find_lines("fast", contents).collect();
+
+        assert_eq!(
+            vec![Match {
+                line_number: 2,
+                byte_offset: 11,
+                line: "fasfast"
+            }],
+            found
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// Unicode-aware matching.
+//
+// "café" can be written with a precomposed é (U+00E9) or as "cafe" followed by a
+// combining acute accent (U+0301). They look the same and Unicode says they are
+// the same text, but `contains` compares bytes and sees two different strings.
+//
+// Before comparing, both the query and the line are folded:
+// - always: canonical decomposition (NFD), so precomposed and combining forms agree,
+//   and the accents on a letter put in canonical order, so "q\u{307}\u{323}" and
+//   "q\u{323}\u{307}" agree too. Text that is equal in NFD is equal in NFC, so this
+//   finds what comparing composed forms would, and can tell "cafe" from "café".
+// - with case-insensitive search: lowercase
+// - with --ignore-diacritics: compatibility decomposition (NFKD) and the accents dropped,
+//   so "CAFÉ", "café" and "cafe" all match each other
+//
+// Reordering moves accents around within a letter, so offsets are kept per letter:
+// a starter and the combining marks after it. Every folded byte remembers where its
+// letter starts in the original, so a match can be reported against the text as it is
+// in the file.
+
+use crate::literal::find_ascii;
+use std::ops::Range;
+use unicode_normalization::char::{canonical_combining_class, is_combining_mark};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Folding {
+    pub ignore_case: bool,
+    pub ignore_diacritics: bool,
+}
+
+impl Folding {
+    pub fn fold(&self, text: &str) -> String {
+        self.fold_with_offsets(text).0
+    }
+
+    // Returns the folded text and, for every byte of it, the offset in `text`
+    // of the character it was produced from.
+    fn fold_with_offsets(&self, text: &str) -> (String, Vec<usize>) {
+        let mut folded = String::with_capacity(text.len());
+        let mut offsets = Vec::with_capacity(text.len());
+
+        for (offset, letter) in letters(text) {
+            // most letters of most lines: an ASCII character without accents decomposes to itself
+            if let [byte] = letter.as_bytes() {
+                if byte.is_ascii() {
+                    folded.push(if self.ignore_case {
+                        byte.to_ascii_lowercase()
+                    } else {
+                        *byte
+                    } as char);
+                    offsets.push(offset);
+                    continue;
+                }
+            }
+            if self.ignore_diacritics {
+                self.push(letter.nfkd(), &mut folded);
+            } else {
+                self.push(letter.nfd(), &mut folded);
+            }
+            offsets.resize(folded.len(), offset);
+        }
+
+        (folded, offsets)
+    }
+
+    fn push<I: Iterator<Item = char>>(&self, decomposed: I, folded: &mut String) {
+        for c in decomposed {
+            if self.ignore_diacritics && is_combining_mark(c) {
+                continue;
+            }
+            if self.ignore_case {
+                folded.extend(c.to_lowercase());
+            } else {
+                folded.push(c);
+            }
+        }
+    }
+
+    // Finds the first occurrence of an already folded query in `line`
+    // and returns its byte range in the original, unfolded line.
+    pub fn find(&self, folded_query: &str, line: &str) -> Option<Range<usize>> {
+        self.find_up_to(1, folded_query, line).pop()
+    }
+
+    // Every occurrence, left to right and not overlapping, e.g. for --format=vimgrep
+    pub fn find_all(&self, folded_query: &str, line: &str) -> Vec<Range<usize>> {
+        self.find_up_to(usize::MAX, folded_query, line)
+    }
+
+    fn find_up_to(&self, limit: usize, folded_query: &str, line: &str) -> Vec<Range<usize>> {
+        // Like str::contains, an empty query is found at the start of every line
+        if folded_query.is_empty() {
+            return vec![Range { start: 0, end: 0 }];
+        }
+
+        // Folding an ASCII line at most lowercases it, so it can be searched as it is
+        if folded_query.is_ascii() && line.is_ascii() {
+            let query = folded_query.as_bytes();
+            let mut found_ranges = Vec::new();
+            let mut from = 0;
+            while found_ranges.len() < limit {
+                match find_ascii(query, line.as_bytes(), self.ignore_case, from) {
+                    Some(start) => {
+                        found_ranges.push(start..start + query.len());
+                        from = start + query.len();
+                    }
+                    None => break,
+                }
+            }
+            return found_ranges;
+        }
+
+        let (folded, offsets) = self.fold_with_offsets(line);
+        let mut found_ranges = Vec::new();
+        let mut from = 0;
+
+        while let Some(found) = folded[from..].find(folded_query) {
+            let start = from + found;
+            let end = start + folded_query.len();
+
+            // A match has to end on a character boundary of the original line:
+            // "cafe" must not match the first half of "café".
+            let on_boundary = end == folded.len() || offsets[end] != offsets[end - 1];
+            let splits_accent = !self.ignore_diacritics
+                && folded[end..].chars().next().is_some_and(is_combining_mark);
+
+            if on_boundary && !splits_accent {
+                let original_end = if end == folded.len() {
+                    line.len()
+                } else {
+                    offsets[end]
+                };
+                found_ranges.push(offsets[start]..original_end);
+                if found_ranges.len() == limit {
+                    break;
+                }
+                from = end;
+            } else {
+                from = start + folded[start..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+
+        found_ranges
+    }
+}
+
+// text cut before every starter, so each piece is a character with the marks on it,
+// and where the piece starts
+fn letters(text: &str) -> Vec<(usize, &str)> {
+    let mut starts: Vec<usize> = text
+        .char_indices()
+        .filter(|&(at, c)| at == 0 || canonical_combining_class(c) == 0)
+        .map(|(at, _)| at)
+        .collect();
+    starts.push(text.len());
+
+    starts
+        .windows(2)
+        .map(|piece| (piece[0], &text[piece[0]..piece[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXACT: Folding = Folding {
+        ignore_case: false,
+        ignore_diacritics: false,
+    };
+
+    const LOOSE: Folding = Folding {
+        ignore_case: true,
+        ignore_diacritics: true,
+    };
+
+    #[test]
+    fn precomposed_matches_combining() {
+        let query = EXACT.fold("cafe\u{301}");
+        let line = "un caf\u{e9} noir";
+
+        assert_eq!(Some(3..8), EXACT.find(&query, line));
+    }
+
+    #[test]
+    fn accents_in_any_order() {
+        // q with a dot above and a dot below, the marks typed in either order
+        let query = EXACT.fold("q\u{307}\u{323}");
+        assert_eq!(Some(1..6), EXACT.find(&query, " q\u{323}\u{307} "));
+
+        // the same with a precomposed d with dot above, and dot below after it
+        let query = EXACT.fold("d\u{323}\u{307}");
+        assert_eq!(Some(0..5), EXACT.find(&query, "\u{1e0b}\u{323}!"));
+        assert_eq!(Some(0..5), EXACT.find(&query, "\u{1e0d}\u{307}"));
+
+        // a letter with one of the two accents is a different letter
+        assert_eq!(None, EXACT.find(&EXACT.fold("q\u{323}"), "q\u{307}\u{323}"));
+    }
+
+    #[test]
+    fn accent_is_not_split() {
+        let query = EXACT.fold("cafe");
+
+        assert_eq!(None, EXACT.find(&query, "caf\u{e9}"));
+        assert_eq!(None, EXACT.find(&query, "cafe\u{301}"));
+        assert_eq!(Some(7..11), EXACT.find(&query, "cafe\u{301} cafe"));
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(Some(0..0), EXACT.find("", "Pick three."));
+        assert_eq!(Some(0..0), EXACT.find("", ""));
+    }
+
+    #[test]
+    fn find_all_occurrences() {
+        let query = LOOSE.fold("cafe");
+
+        assert_eq!(
+            vec![0..5, 8..12, 15..21],
+            LOOSE.find_all(&query, "caf\u{e9} + cafe = cafe\u{301}")
+        );
+    }
+
+    #[test]
+    fn ignore_diacritics() {
+        let query = LOOSE.fold("CAFE");
+
+        assert_eq!(Some(8..13), LOOSE.find(&query, "Un th\u{e9} caf\u{e9}"));
+        assert_eq!(Some(0..6), LOOSE.find(&query, "Cafe\u{301}!"));
+    }
+
+    #[test]
+    fn offsets_point_into_the_original_text() {
+        let query = LOOSE.fold("resume");
+        let line = "\u{c9}t\u{e9}: R\u{c9}SUM\u{c9}";
+
+        let range = LOOSE.find(&query, line).unwrap();
+
+        assert_eq!("R\u{c9}SUM\u{c9}", &line[range]);
+    }
+}
@@ -0,0 +1,384 @@
+// A small boolean query language, for `minigrep2 --query` and SearcherBuilder::build_query.
+//
+//     timeout AND (retry OR backoff) NOT test
+//     "connection reset" OR refused
+//     timeout NEAR/5 retry
+//
+// Operators are written in capitals so that "and", "or" and "not" can still be searched for.
+// From tightest to loosest they bind: NOT (in front of a term), NEAR/n, AND, OR.
+// `a NOT b` means `a AND NOT b`, and two terms next to each other mean AND.
+//
+// A term matches when a plain search would find it, so ignoring case and
+// diacritics applies to every term. NEAR/n matches when the two words are at most
+// n words apart. With Scope::Paragraph the expression is checked against whole
+// paragraphs (blocks separated by blank lines) instead of single lines.
+
+use crate::{Folding, Match};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Line,
+    Paragraph,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Term(String),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Near(String, String, usize),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Term(String),
+    And,
+    Or,
+    Not,
+    Near(usize),
+    Open,
+    Close,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let query = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(query),
+            Some(Token::Close) => Err(String::from("Unmatched `)` in query")),
+            Some(token) => Err(format!("Unexpected {:?} in query", token)),
+        }
+    }
+
+    pub fn matches(&self, folding: Folding, text: &str) -> bool {
+        match self {
+            Query::Term(term) => folding.find(&folding.fold(term), text).is_some(),
+            Query::Not(query) => !query.matches(folding, text),
+            Query::And(left, right) => left.matches(folding, text) && right.matches(folding, text),
+            Query::Or(left, right) => left.matches(folding, text) || right.matches(folding, text),
+            Query::Near(left, right, distance) => near(folding, text, left, right, *distance),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if phrase.is_empty() {
+                return Err(String::from("Empty phrase in query"));
+            }
+            tokens.push(Token::Term(phrase));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+
+            tokens.push(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ if word.starts_with("NEAR/") => match word["NEAR/".len()..].parse() {
+                    Ok(distance) => Token::Near(distance),
+                    Err(_) => return Err(format!("`{}` needs a number, like NEAR/5", word)),
+                },
+                _ => Token::Term(word),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+// A recursive descent parser: one method per precedence level, loosest first.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.near()?;
+        loop {
+            query = match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    Query::And(Box::new(query), Box::new(self.near()?))
+                }
+                // `a NOT b`: NOT between two operands means AND NOT
+                Some(Token::Not) => {
+                    self.next();
+                    let right = Query::Not(Box::new(self.near()?));
+                    Query::And(Box::new(query), Box::new(right))
+                }
+                // `a b`: nothing in between means AND
+                Some(Token::Term(_)) | Some(Token::Open) => {
+                    Query::And(Box::new(query), Box::new(self.near()?))
+                }
+                _ => return Ok(query),
+            };
+        }
+    }
+
+    fn near(&mut self) -> Result<Query, String> {
+        let left = self.unary()?;
+        let distance = match self.peek() {
+            Some(Token::Near(distance)) => *distance,
+            _ => return Ok(left),
+        };
+        self.next();
+        let right = self.unary()?;
+
+        match (left, right) {
+            (Query::Term(left), Query::Term(right))
+                if !left.contains(' ') && !right.contains(' ') =>
+            {
+                Ok(Query::Near(left, right, distance))
+            }
+            _ => Err(format!(
+                "NEAR/{} needs a single word on each side",
+                distance
+            )),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Query, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Term(term)) => Ok(Query::Term(term.clone())),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(String::from("Missing `)` in query")),
+                }
+            }
+            Some(token) => Err(format!("Expected a term but found {:?}", token)),
+            None => Err(String::from("Query ends too early")),
+        }
+    }
+}
+
+// Word positions are counted over the whole text, so in paragraph scope the
+// two words may be on different lines.
+fn near(folding: Folding, text: &str, left: &str, right: &str, distance: usize) -> bool {
+    let left = folding.fold(left);
+    let right = folding.fold(right);
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| folding.fold(word))
+        .collect();
+
+    let positions = |term: &str| -> Vec<usize> {
+        words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| word.as_str() == term)
+            .map(|(index, _)| index)
+            .collect()
+    };
+
+    let right_positions = positions(&right);
+    positions(&left).iter().any(|&l| {
+        right_positions
+            .iter()
+            .any(|&r| l != r && (l as isize - r as isize).unsigned_abs() <= distance)
+    })
+}
+
+// Runs a query over every line or every paragraph of contents.
+// A paragraph is reported as one Match whose line spans the whole block.
+pub(crate) fn search_query<'a>(
+    query: &Query,
+    folding: Folding,
+    invert_match: bool,
+    scope: Scope,
+    contents: &'a str,
+) -> Vec<Match<'a>> {
+    let blocks = match scope {
+        Scope::Line => contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, offset_of(contents, line), line))
+            .collect(),
+        Scope::Paragraph => paragraphs(contents),
+    };
+
+    blocks
+        .into_iter()
+        .filter(|(_, _, text)| query.matches(folding, text) != invert_match)
+        .map(|(line_number, byte_offset, line)| Match {
+            line_number,
+            byte_offset,
+            line,
+        })
+        .collect()
+}
+
+fn offset_of(contents: &str, part: &str) -> usize {
+    part.as_ptr() as usize - contents.as_ptr() as usize
+}
+
+// (first line number, byte offset, text) of every block of non-blank lines
+fn paragraphs(contents: &str) -> Vec<(usize, usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(usize, usize, usize)> = None;
+
+    for (index, line) in contents.lines().enumerate() {
+        let start = offset_of(contents, line);
+        if line.trim().is_empty() {
+            if let Some((number, from, to)) = current.take() {
+                blocks.push((number, from, &contents[from..to]));
+            }
+        } else {
+            let end = start + line.len();
+            current = match current {
+                Some((number, from, _)) => Some((number, from, end)),
+                None => Some((index + 1, start, end)),
+            };
+        }
+    }
+
+    if let Some((number, from, to)) = current {
+        blocks.push((number, from, &contents[from..to]));
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXACT: Folding = Folding {
+        ignore_case: false,
+        ignore_diacritics: false,
+    };
+
+    fn term(word: &str) -> Box<Query> {
+        Box::new(Query::Term(String::from(word)))
+    }
+
+    #[test]
+    fn parse_precedence() {
+        assert_eq!(
+            Ok(Query::And(
+                Box::new(Query::And(
+                    term("timeout"),
+                    Box::new(Query::Or(term("retry"), term("backoff")))
+                )),
+                Box::new(Query::Not(term("test")))
+            )),
+            Query::parse("timeout AND (retry OR backoff) NOT test")
+        );
+        assert_eq!(
+            Ok(Query::Or(
+                Box::new(Query::And(term("a"), term("b"))),
+                term("c")
+            )),
+            Query::parse("a b OR c")
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Query::parse("(timeout OR retry").is_err());
+        assert!(Query::parse("timeout OR").is_err());
+        assert!(Query::parse("timeout)").is_err());
+        assert!(Query::parse("timeout NEAR/x retry").is_err());
+        assert!(Query::parse("(a OR b) NEAR/2 c").is_err());
+    }
+
+    #[test]
+    fn line_scope() {
+        let query = Query::parse("timeout AND (retry OR backoff) NOT test").unwrap();
+        let contents = "\
+timeout, retry in 5s
+timeout, giving up
+test: timeout then retry
+backoff after timeout";
+
+        let lines: Vec<&str> = search_query(&query, EXACT, false, Scope::Line, contents)
+            .into_iter()
+            .map(|found| found.line)
+            .collect();
+
+        assert_eq!(vec!["timeout, retry in 5s", "backoff after timeout"], lines);
+    }
+
+    #[test]
+    fn near_counts_words() {
+        let query = Query::parse("timeout NEAR/2 retry").unwrap();
+
+        assert!(query.matches(EXACT, "timeout, will retry"));
+        assert!(query.matches(EXACT, "retry after timeout"));
+        assert!(!query.matches(EXACT, "timeout and then we retry"));
+    }
+
+    #[test]
+    fn paragraph_scope() {
+        let query = Query::parse("timeout AND retry").unwrap();
+        let contents = "\
+connect: timeout
+
+retry 1: timeout
+retry 2: ok
+
+done";
+
+        let found = search_query(&query, EXACT, false, Scope::Paragraph, contents);
+
+        assert_eq!(
+            vec![Match {
+                line_number: 3,
+                byte_offset: 18,
+                line: "retry 1: timeout\nretry 2: ok"
+            }],
+            found
+        );
+    }
+}
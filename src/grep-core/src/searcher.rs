@@ -0,0 +1,297 @@
+// Building and running a search.
+//
+// A SearcherBuilder collects the options, and build (a plain string) or build_query
+// (a Query) turns them into a Searcher for one pattern. The builder can be kept and
+// used again, e.g. by a picker that searches on every keystroke.
+//
+// The Searcher picks the cheapest way to get the same answer: for a query that is ASCII
+// once folded, a byte scan that skips from hit to hit and only folds the lines with other
+// characters in them (see literal.rs); otherwise folding line by line.
+
+use crate::literal::find_folded_lines;
+use crate::query::search_query;
+use crate::{Folding, Match, Query, Scope, Sink};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearcherBuilder {
+    ignore_case: bool,
+    ignore_diacritics: bool,
+    invert_match: bool,
+    scope: Scope,
+}
+
+impl SearcherBuilder {
+    pub const fn new() -> SearcherBuilder {
+        SearcherBuilder {
+            ignore_case: false,
+            ignore_diacritics: false,
+            invert_match: false,
+            scope: Scope::Line,
+        }
+    }
+
+    // "rust" matches "Rust" and "RUST"
+    pub fn ignore_case(mut self, yes: bool) -> SearcherBuilder {
+        self.ignore_case = yes;
+        self
+    }
+
+    // "cafe" matches "café"
+    pub fn ignore_diacritics(mut self, yes: bool) -> SearcherBuilder {
+        self.ignore_diacritics = yes;
+        self
+    }
+
+    // Select the lines that do NOT match
+    pub fn invert_match(mut self, yes: bool) -> SearcherBuilder {
+        self.invert_match = yes;
+        self
+    }
+
+    // What a Query is checked against; plain strings are always searched line by line
+    pub fn scope(mut self, scope: Scope) -> SearcherBuilder {
+        self.scope = scope;
+        self
+    }
+
+    pub fn folding(&self) -> Folding {
+        Folding {
+            ignore_case: self.ignore_case,
+            ignore_diacritics: self.ignore_diacritics,
+        }
+    }
+
+    // A searcher for lines containing pattern
+    pub fn build(&self, pattern: &str) -> Searcher {
+        Searcher {
+            options: *self,
+            pattern: Pattern::Literal {
+                folded: self.folding().fold(pattern),
+            },
+        }
+    }
+
+    // A searcher for lines (or paragraphs) matching query
+    pub fn build_query(&self, query: Query) -> Searcher {
+        Searcher {
+            options: *self,
+            pattern: Pattern::Query(query),
+        }
+    }
+}
+
+impl Default for SearcherBuilder {
+    fn default() -> SearcherBuilder {
+        SearcherBuilder::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Literal { folded: String },
+    Query(Query),
+}
+
+#[derive(Debug, Clone)]
+pub struct Searcher {
+    options: SearcherBuilder,
+    pattern: Pattern,
+}
+
+// The selected lines of one text, in order
+pub struct Matches<'s, 'a> {
+    inner: Box<dyn Iterator<Item = Match<'a>> + 's>,
+}
+
+impl<'a> Iterator for Matches<'_, 'a> {
+    type Item = Match<'a>;
+
+    fn next(&mut self) -> Option<Match<'a>> {
+        self.inner.next()
+    }
+}
+
+impl Searcher {
+    pub fn folding(&self) -> Folding {
+        self.options.folding()
+    }
+
+    pub fn matches<'s, 'a: 's>(&'s self, contents: &'a str) -> Matches<'s, 'a> {
+        let inner: Box<dyn Iterator<Item = Match<'a>> + 's> = match &self.pattern {
+            Pattern::Query(query) => Box::new(
+                search_query(
+                    query,
+                    self.folding(),
+                    self.options.invert_match,
+                    self.options.scope,
+                    contents,
+                )
+                .into_iter(),
+            ),
+            Pattern::Literal { folded } if self.can_skip_lines(folded) => {
+                Box::new(find_folded_lines(folded, self.folding(), contents))
+            }
+            Pattern::Literal { .. } => return self.slow_matches(contents),
+        };
+
+        Matches { inner }
+    }
+
+    // -v selects the lines without a hit, so it has to go through all of them
+    fn can_skip_lines(&self, folded: &str) -> bool {
+        !self.options.invert_match && folded.is_ascii() && !folded.contains(['\n', '\r'])
+    }
+
+    // Compares every line, folding those that need it; works for every option
+    pub(crate) fn slow_matches<'s, 'a: 's>(&'s self, contents: &'a str) -> Matches<'s, 'a> {
+        let invert_match = self.options.invert_match;
+
+        Matches {
+            inner: Box::new(
+                contents
+                    .lines()
+                    .enumerate()
+                    .filter_map(move |(index, line)| {
+                        // lines() hands out slices of contents, so the distance between the two pointers is the line's offset
+                        let line_start = line.as_ptr() as usize - contents.as_ptr() as usize;
+                        let found = self.find(line);
+                        if found.is_some() == invert_match {
+                            return None;
+                        }
+                        Some(Match {
+                            line_number: index + 1,
+                            byte_offset: line_start + found.map_or(0, |range| range.start),
+                            line,
+                        })
+                    }),
+            ),
+        }
+    }
+
+    // Passes every selected line to sink, until it has seen them all or asks to stop
+    pub fn search<S: Sink>(&self, contents: &str, sink: &mut S) -> Result<(), S::Error> {
+        for found in self.matches(contents) {
+            if !sink.matched(&found)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Whether the pattern is in text at all, leaving invert_match aside
+    pub fn contains(&self, text: &str) -> bool {
+        match &self.pattern {
+            Pattern::Literal { folded } => self.folding().find(folded, text).is_some(),
+            Pattern::Query(query) => query.matches(self.folding(), text),
+        }
+    }
+
+    // Where the first match is in line. A Query selects whole lines, so that is the line's start.
+    pub fn find(&self, line: &str) -> Option<Range<usize>> {
+        match &self.pattern {
+            Pattern::Literal { folded } => self.folding().find(folded, line),
+            Pattern::Query(query) => Some(0..0).filter(|_| query.matches(self.folding(), line)),
+        }
+    }
+
+    // Every match in line, left to right and not overlapping
+    pub fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        match &self.pattern {
+            Pattern::Literal { folded } => self.folding().find_all(folded, line),
+            Pattern::Query(_) => self.find(line).into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+    fn lines(searcher: &Searcher, contents: &str) -> Vec<String> {
+        searcher
+            .matches(contents)
+            .map(|found| found.line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn builder_options() {
+        let builder = SearcherBuilder::new();
+
+        assert_eq!(vec!["Trust me."], lines(&builder.build("rust"), POEM));
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            lines(&builder.ignore_case(true).build("rust"), POEM)
+        );
+        assert_eq!(
+            vec!["Rust:", "safe, fast, productive.", "Pick three."],
+            lines(&builder.invert_match(true).build("rust"), POEM)
+        );
+        assert_eq!(
+            vec!["Pick three.", "Trust me."],
+            lines(
+                &builder.build_query(Query::parse("e NOT fast").unwrap()),
+                POEM
+            )
+        );
+    }
+
+    #[test]
+    fn fast_and_slow_paths_agree() {
+        let accented = "Cr\u{e8}me br\u{fb}l\u{e9}e\nCREME brulee\ncre\u{300}me fra\u{ee}che";
+        let builders = [
+            SearcherBuilder::new(),
+            SearcherBuilder::new().ignore_case(true),
+            SearcherBuilder::new()
+                .ignore_case(true)
+                .ignore_diacritics(true),
+        ];
+
+        for builder in &builders {
+            for pattern in ["fast", "t", "", ":", "nowhere", "creme", "Cr", "e"] {
+                let searcher = builder.build(pattern);
+
+                for contents in [POEM, accented] {
+                    assert_eq!(
+                        searcher.slow_matches(contents).collect::<Vec<Match>>(),
+                        searcher.matches(contents).collect::<Vec<Match>>(),
+                        "pattern {:?} with {:?}",
+                        pattern,
+                        builder
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_outlive_the_searcher() {
+        let found: Vec<Match> = SearcherBuilder::new().build("fast").matches(POEM).collect();
+
+        assert_eq!(
+            vec![Match {
+                line_number: 2,
+                byte_offset: 12,
+                line: "safe, fast, productive."
+            }],
+            found
+        );
+    }
+
+    #[test]
+    fn find_points_into_the_line() {
+        let searcher = SearcherBuilder::new().ignore_case(true).build("T");
+
+        assert_eq!(Some(3..4), searcher.find("Rust:"));
+        assert_eq!(vec![0..1, 4..5], searcher.find_all("Trust me."));
+        assert!(searcher.contains("fast"));
+        assert!(!searcher.contains("Pick"));
+    }
+}
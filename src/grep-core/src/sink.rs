@@ -0,0 +1,115 @@
+// Where a search sends the lines it selects.
+//
+// Searcher::search hands every selected line to a Sink as soon as it is found, so a
+// caller can print, count or collect them without holding all of them in memory.
+// Returning Ok(false) from matched stops the search, e.g. after the first hit.
+
+use crate::Match;
+use std::io::{self, Write};
+
+pub trait Sink {
+    type Error;
+
+    fn matched(&mut self, found: &Match) -> Result<bool, Self::Error>;
+}
+
+// Counts the selected lines, like grep -c
+#[derive(Debug, Default)]
+pub struct Count(pub usize);
+
+impl Sink for Count {
+    type Error = io::Error;
+
+    fn matched(&mut self, _found: &Match) -> Result<bool, io::Error> {
+        self.0 += 1;
+        Ok(true)
+    }
+}
+
+// Writes each selected line, optionally after its line number, like grep and grep -n
+pub struct Print<W: Write> {
+    out: W,
+    line_number: bool,
+}
+
+impl<W: Write> Print<W> {
+    pub fn new(out: W) -> Print<W> {
+        Print {
+            out,
+            line_number: false,
+        }
+    }
+
+    pub fn line_number(mut self, yes: bool) -> Print<W> {
+        self.line_number = yes;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Sink for Print<W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, found: &Match) -> Result<bool, io::Error> {
+        if self.line_number {
+            write!(self.out, "{}:", found.line_number)?;
+        }
+        writeln!(self.out, "{}", found.line)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearcherBuilder;
+
+    const POEM: &str = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+    // Keeps the first line number it sees and stops
+    struct First(Option<usize>);
+
+    impl Sink for First {
+        type Error = io::Error;
+
+        fn matched(&mut self, found: &Match) -> Result<bool, io::Error> {
+            self.0 = Some(found.line_number);
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn print_and_count() {
+        let searcher = SearcherBuilder::new().ignore_case(true).build("rust");
+
+        let mut print = Print::new(Vec::new()).line_number(true);
+        searcher.search(POEM, &mut print).unwrap();
+        assert_eq!(
+            "1:Rust:\n4:Trust me.\n",
+            String::from_utf8(print.into_inner()).unwrap()
+        );
+
+        let mut count = Count::default();
+        searcher.search(POEM, &mut count).unwrap();
+        assert_eq!(2, count.0);
+    }
+
+    #[test]
+    fn a_sink_can_stop_the_search() {
+        let mut first = First(None);
+
+        SearcherBuilder::new()
+            .build("e")
+            .search(POEM, &mut first)
+            .unwrap();
+
+        assert_eq!(Some(2), first.0);
+    }
+}
@@ -1,6 +1,10 @@
 use std::env;
 use std::error::Error;
 use std::fs;
+
+// The search functions now live in grep-core, shared with minigrep2.
+// They compare lines with contains as they always have, without minigrep2's Unicode folding.
+pub use grep_core::{search, search_case_insensitive};
 /**
 We’ve made liberal use of the pub keyword: on Config, on its fields and its new method, and on the run function.
 We now have a library crate that has a public API that we can test!
@@ -60,40 +64,23 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-// lifetime 'a used with the contents argument and the return value.
-// the lifetime parameters specify which argument lifetime is connected to the lifetime of the return value.
-// In this case, we indicate that the returned vector should contain string slices that reference slices of the argument contents (rather than the argument query).
-// n other words, we tell Rust that the data returned by the search function will live as long as the data passed into the search function in the contents argument.
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let mut results = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for line in contents.lines() {
-        if line.contains(query) {
-            results.push(line);
-        }
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
     }
 
-    results
-}
-
-// CASE_INSENSITIVE=1 cargo run to poem.txt
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
-    let mut results = Vec::new();
+    #[test]
+    fn config_takes_query_and_filename() {
+        let config = Config::new(&args(&["minigrep", "duct", "poem.txt"])).unwrap();
 
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
-        }
+        assert_eq!("duct", config.query);
+        assert_eq!("poem.txt", config.filename);
+        assert!(Config::new(&args(&["minigrep", "duct"])).is_err());
     }
 
-    results
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
     #[test]
     fn case_sensitive() {
         let query = "duct";
@@ -120,4 +107,13 @@ Trust me.";
             search_case_insensitive(query, contents)
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bytes_are_compared_as_they_are() {
+        // an e with a combining accent is still an e followed by the accent
+        let contents = "cafe\u{301}\ncaf\u{e9}";
+
+        assert_eq!(vec!["cafe\u{301}"], search("cafe", contents));
+        assert_eq!(vec!["caf\u{e9}"], search("caf\u{e9}", contents));
+    }
+}
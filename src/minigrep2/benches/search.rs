@@ -0,0 +1,156 @@
+// Times the search `run` used to do (read the file into a String, then `line.contains`
+// on every line) against what `run` does now: map the file and hand it to the Searcher
+// built from the command line, through search_lines.
+//
+// Each search runs on a log that is all ASCII and on one where every hundredth line has
+// accented words in it, with and without -i, and once with -v.
+//
+//     cargo bench                           # 1 GB files
+//     MINIGREP2_BENCH_MB=100 cargo bench    # something quicker
+//
+// The files are written to the temp directory once and reused by later runs of the same size.
+
+use minigrep2::{search, search_case_insensitive, search_lines, Config, Contents, Settings};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const QUERY: &str = "connection reset";
+
+// Runs of each search; the fastest one is reported
+const RUNS: usize = 3;
+
+fn bench_file(megabytes: u64, accented: bool) -> PathBuf {
+    let name = if accented { "accented" } else { "ascii" };
+    let path = env::temp_dir().join(format!("minigrep2-bench-{}-{}mb.log", name, megabytes));
+    let size = megabytes * 1024 * 1024;
+    if fs::metadata(&path)
+        .map(|m| m.len() >= size)
+        .unwrap_or(false)
+    {
+        return path;
+    }
+
+    // Log-like lines, one in ten thousand containing the query
+    let mut out = BufWriter::new(File::create(&path).unwrap());
+    let mut written = 0;
+    let mut number: u64 = 0;
+    while written < size {
+        let line = if number.is_multiple_of(10_000) {
+            format!(
+                "2024-03-01T12:00:00Z worker-{} error: connection reset by peer\n",
+                number % 64
+            )
+        } else if accented && number % 100 == 1 {
+            format!(
+                "2024-03-01T12:00:00Z worker-{} info: requête {} traitée, café crème à emporter\n",
+                number % 64,
+                number
+            )
+        } else {
+            format!(
+                "2024-03-01T12:00:00Z worker-{} info: request {} served in {}ms\n",
+                number % 64,
+                number,
+                number % 997
+            )
+        };
+        out.write_all(line.as_bytes()).unwrap();
+        written += line.len() as u64;
+        number += 1;
+    }
+    out.flush().unwrap();
+
+    path
+}
+
+fn fastest<F: FnMut() -> usize>(mut search: F) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut found = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        found = search();
+        best = best.min(start.elapsed());
+    }
+    (best, found)
+}
+
+fn report(name: &str, megabytes: u64, (time, found): (Duration, usize)) {
+    println!(
+        "  {:<22} {:>8.3}s {:>9.0} MB/s   {} lines",
+        name,
+        time.as_secs_f64(),
+        megabytes as f64 / time.as_secs_f64(),
+        found
+    );
+}
+
+// The old run: the whole file in a String, then the tutorial search
+fn read_and_search(path: &Path, options: &[&str]) -> usize {
+    let contents = fs::read_to_string(path).unwrap();
+    if options.contains(&"-v") {
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.contains(QUERY))
+            .collect();
+        lines.len()
+    } else if options.contains(&"-i") {
+        search_case_insensitive(QUERY, &contents).len()
+    } else {
+        search(QUERY, &contents).len()
+    }
+}
+
+// The way run searches a file now
+fn map_and_search(config: &Config, path: &Path) -> usize {
+    let contents = Contents::map(&File::open(path).unwrap()).unwrap().unwrap();
+    search_lines(config, &contents).len()
+}
+
+fn main() {
+    // `cargo bench` passes --bench; `cargo test` runs benches with nothing, and shouldn't write a gigabyte
+    if !env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+
+    let megabytes = env::var("MINIGREP2_BENCH_MB")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1024);
+
+    for accented in [false, true] {
+        let path = bench_file(megabytes, accented);
+        for options in [&[][..], &["-i"], &["-v"]] {
+            let positional = [QUERY, path.to_str().unwrap()];
+            let args = ["minigrep2"]
+                .iter()
+                .chain(options)
+                .chain(&positional)
+                .map(|arg| arg.to_string());
+            let config = Config::with_settings(args, Settings::new()).unwrap();
+
+            println!(
+                "{} MB {} log, {:?} {}",
+                megabytes,
+                if accented { "accented" } else { "ASCII" },
+                QUERY,
+                options.join(" ")
+            );
+            let before = fastest(|| read_and_search(&path, options));
+            let after = fastest(|| map_and_search(&config, &path));
+            assert_eq!(
+                before.1, after.1,
+                "both searches should find the same lines"
+            );
+
+            report("read + search", megabytes, before);
+            report("mmap + Searcher", megabytes, after);
+            println!(
+                "  speedup: {:.1}x",
+                before.0.as_secs_f64() / after.0.as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// --search-archives: look inside .tar, .tar.gz and .zip files.
+//
+// Every regular file inside the archive that is valid UTF-8 becomes an Entry that can be
+// searched like any other file. Entries that expand far more than they were compressed
+// are refused, so a small "zip bomb" can't make us allocate gigabytes.
+//
+// A zip entry that can't be read is skipped with a note on stderr and the rest of the
+// archive is still searched. An archive that can't be read at all, or a tarball past its
+// budget, is an Err; load_sources notes it and goes on with the other files.
+
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read, Seek};
+
+// An entry may be at most this many times bigger than its compressed form.
+// Ordinary text compresses 3-10x; anything beyond this is almost certainly hostile.
+pub const MAX_COMPRESSION_RATIO: u64 = 100;
+
+pub struct Entry {
+    pub name: String,
+    pub contents: String,
+}
+
+pub fn is_archive(filename: &str) -> bool {
+    filename.ends_with(".tar")
+        || filename.ends_with(".tar.gz")
+        || filename.ends_with(".tgz")
+        || filename.ends_with(".zip")
+}
+
+pub fn read_entries(filename: &str) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let file = File::open(filename)?;
+    let compressed_len = file.metadata()?.len();
+
+    if filename.ends_with(".zip") {
+        read_zip(file)
+    } else if filename.ends_with(".tar") {
+        read_tar(file, None)
+    } else {
+        // A gzip stream is one compressed blob, so the whole tarball shares a single budget.
+        let limit = compressed_len.saturating_mul(MAX_COMPRESSION_RATIO);
+        read_tar(GzDecoder::new(file), Some(limit))
+    }
+}
+
+pub fn read_zip<R: Read + Seek>(reader: R) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut entries = Vec::new();
+
+    for index in 0..archive.len() {
+        let file = match archive.by_index(index) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("entry {}: skipped, {}", index + 1, e);
+                continue;
+            }
+        };
+        if file.is_dir() {
+            continue;
+        }
+
+        let name = file.name().to_string();
+        let size = file.size();
+        let compressed_size = file.compressed_size().max(1);
+
+        if size / compressed_size > MAX_COMPRESSION_RATIO {
+            eprintln!(
+                "{}: skipped, expands {}x (limit is {}x)",
+                name,
+                size / compressed_size,
+                MAX_COMPRESSION_RATIO
+            );
+            continue;
+        }
+
+        // The sizes in the header could be lying, so never read more than they promised.
+        let mut bytes = Vec::new();
+        if let Err(e) = file.take(size + 1).read_to_end(&mut bytes) {
+            eprintln!("{}: skipped, {}", name, e);
+            continue;
+        }
+        if bytes.len() as u64 > size {
+            eprintln!("{}: skipped, larger than its header says", name);
+            continue;
+        }
+
+        if let Some(entry) = text_entry(name, bytes) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+// `limit` caps the total number of bytes unpacked from a compressed tarball.
+pub fn read_tar<R: Read>(reader: R, limit: Option<u64>) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    let mut unpacked: u64 = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry.path()?.to_string_lossy().into_owned();
+        unpacked += entry.size();
+        if let Some(limit) = limit {
+            if unpacked > limit {
+                return Err(Box::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: archive expands more than {}x, refusing to unpack it",
+                        name, MAX_COMPRESSION_RATIO
+                    ),
+                )));
+            }
+        }
+
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+
+        if let Some(entry) = text_entry(name, bytes) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+// Only text entries are searched; images, executables and the like are skipped quietly.
+fn text_entry(name: String, bytes: Vec<u8>) -> Option<Entry> {
+    String::from_utf8(bytes)
+        .ok()
+        .map(|contents| Entry { name, contents })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn zip_with(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, contents) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    fn tar_with(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn zip_text_entries_are_read() {
+        let archive = zip_with(&[
+            ("docs/poem.txt", b"Rust:\nsafe, fast, productive."),
+            ("logo.png", &[0x89, 0x50, 0xff, 0xfe]),
+        ]);
+
+        let entries = read_zip(archive).unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!("docs/poem.txt", entries[0].name);
+        assert_eq!("Rust:\nsafe, fast, productive.", entries[0].contents);
+    }
+
+    #[test]
+    fn zip_bomb_entries_are_skipped() {
+        let zeros = vec![b'0'; 1024 * 1024];
+        let archive = zip_with(&[("bomb.txt", &zeros), ("poem.txt", b"Pick three.")]);
+
+        let entries = read_zip(archive).unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!("poem.txt", entries[0].name);
+    }
+
+    #[test]
+    fn unreadable_zip_entries_are_skipped() {
+        let mut archive = zip_with(&[
+            ("broken.txt", b"Rust, Rust, Rust, Rust."),
+            ("poem.txt", b"Pick three."),
+        ])
+        .into_inner();
+        // the compressed data of the first entry starts after its 30 byte header and name
+        archive[30 + "broken.txt".len() + 2] ^= 0xff;
+
+        let entries = read_zip(Cursor::new(archive)).unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!("poem.txt", entries[0].name);
+    }
+
+    #[test]
+    fn tar_text_entries_are_read() {
+        let archive = tar_with(&[("inner.txt", b"Trust me.")]);
+
+        let entries = read_tar(Cursor::new(archive), None).unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!("inner.txt", entries[0].name);
+        assert_eq!("Trust me.", entries[0].contents);
+    }
+
+    #[test]
+    fn tar_beyond_the_limit_is_refused() {
+        let archive = tar_with(&[("big.txt", &[b'a'; 4096])]);
+
+        assert!(read_tar(Cursor::new(archive), Some(1024)).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+// --bytes: search binary files.
+//
+// The files are searched as raw bytes, without decoding, so anything can be in them.
+// In the query, \xHH stands for the byte with that hex value and \\ for a backslash;
+// everything else stands for its own UTF-8 bytes:
+//
+//     minigrep2 --bytes '\x7fELF' core.dump
+//     minigrep2 --bytes 'PK\x03\x04' backup.img
+//
+// Each match is printed as a `hexdump -C` style block: the 16 byte rows it touches plus
+// CONTEXT_ROWS rows on either side, under a header with the file and the match's offset.
+// Matches close enough to share rows share a block. -c prints the number of matches instead.
+// --sort orders the files as it does for a text search, count being the number of matches.
+
+use crate::{expand, order, Config};
+use memchr::memmem;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+
+pub const ROW: usize = 16;
+
+// Rows shown before and after the rows of a match
+pub const CONTEXT_ROWS: usize = 1;
+
+pub fn parse_pattern(text: &str) -> Result<Vec<u8>, String> {
+    let mut pattern = Vec::new();
+    let mut rest = text;
+
+    while let Some(at) = rest.find('\\') {
+        pattern.extend_from_slice(&rest.as_bytes()[..at]);
+        rest = &rest[at + 1..];
+
+        if let Some(after) = rest.strip_prefix('\\') {
+            pattern.push(b'\\');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('x') {
+            let digits = after
+                .get(..2)
+                .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()));
+            match digits {
+                Some(digits) => {
+                    pattern.push(u8::from_str_radix(digits, 16).unwrap());
+                    rest = &after[2..];
+                }
+                None => return Err(String::from("\\x needs two hex digits, like \\x0A")),
+            }
+        } else {
+            return Err(String::from("Unknown escape in pattern, use \\xHH or \\\\"));
+        }
+    }
+    pattern.extend_from_slice(rest.as_bytes());
+
+    if pattern.is_empty() {
+        return Err(String::from("--bytes needs a pattern"));
+    }
+    Ok(pattern)
+}
+
+// Where every match starts, left to right and not overlapping
+pub fn find_all(pattern: &[u8], haystack: &[u8]) -> Vec<usize> {
+    memmem::find_iter(haystack, pattern).collect()
+}
+
+// The rows to print, as byte ranges aligned to ROW, each with the matches it shows
+pub fn blocks(
+    pattern_length: usize,
+    matches: &[usize],
+    length: usize,
+) -> Vec<(Range<usize>, Vec<usize>)> {
+    let mut blocks: Vec<(Range<usize>, Vec<usize>)> = Vec::new();
+
+    for &start in matches {
+        let first_row = (start / ROW).saturating_sub(CONTEXT_ROWS);
+        let last_row = (start + pattern_length - 1) / ROW + CONTEXT_ROWS;
+        let rows = first_row * ROW..((last_row + 1) * ROW).min(length);
+
+        match blocks.last_mut() {
+            Some((previous, offsets)) if rows.start <= previous.end => {
+                previous.end = previous.end.max(rows.end);
+                offsets.push(start);
+            }
+            _ => blocks.push((rows, vec![start])),
+        }
+    }
+
+    blocks
+}
+
+// One `hexdump -C` row: offset, the bytes in hex in two groups of eight, then the printable ones
+pub fn hexdump_row(offset: usize, row: &[u8]) -> String {
+    let mut hex = String::new();
+    for index in 0..ROW {
+        if index == ROW / 2 {
+            hex.push(' ');
+        }
+        match row.get(index) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+    }
+
+    let text: String = row
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+
+    format!("{:08x}  {} |{}|", offset, hex, text)
+}
+
+pub fn write_matches<W: Write>(
+    name: &str,
+    pattern: &[u8],
+    haystack: &[u8],
+    count: bool,
+    out: &mut W,
+) -> io::Result<()> {
+    let matches = find_all(pattern, haystack);
+
+    if count {
+        return writeln!(out, "{}:{}", name, matches.len());
+    }
+
+    for (rows, offsets) in blocks(pattern.len(), &matches, haystack.len()) {
+        let offsets: Vec<String> = offsets
+            .iter()
+            .map(|offset| format!("{:#x}", offset))
+            .collect();
+        writeln!(out, "{}: {}", name, offsets.join(", "))?;
+
+        for row_start in rows.clone().step_by(ROW) {
+            let row_end = (row_start + ROW).min(rows.end);
+            writeln!(
+                out,
+                "{}",
+                hexdump_row(row_start, &haystack[row_start..row_end])
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run<W: Write>(config: &Config, pattern: &[u8], out: &mut W) -> Result<(), Box<dyn Error>> {
+    let mut filenames = expand(&config.filenames, &config.ignore)?;
+    if let Some(sort) = config.sort {
+        // an unreadable file sorts as if it had no matches; reading it below reports it
+        order::sort_paths(sort, &mut filenames, |filename| {
+            fs::read(filename).map_or(0, |haystack| find_all(pattern, &haystack).len())
+        });
+    }
+
+    for filename in filenames {
+        let haystack = fs::read(&filename).map_err(|e| format!("{}: {}", filename, e))?;
+        write_matches(&filename, pattern, &haystack, config.count, out)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_escapes() {
+        assert_eq!(Ok(vec![0x00, 0xff]), parse_pattern("\\x00\\xFF"));
+        assert_eq!(
+            Ok(vec![0x7f, b'E', b'L', b'F', b'\\']),
+            parse_pattern("\\x7fELF\\\\")
+        );
+        assert_eq!(Ok("é".as_bytes().to_vec()), parse_pattern("é"));
+
+        assert!(parse_pattern("\\x0").is_err());
+        assert!(parse_pattern("\\xZZ").is_err());
+        assert!(parse_pattern("\\n").is_err());
+        assert!(parse_pattern("").is_err());
+    }
+
+    #[test]
+    fn nearby_matches_share_a_block() {
+        // rows 0-2 for the match at 20, rows 1-3 for 40: one block. Row 9 for 150 is far away.
+        assert_eq!(
+            vec![(0..64, vec![20, 40]), (128..160, vec![150])],
+            blocks(2, &[20, 40, 150], 160)
+        );
+    }
+
+    #[test]
+    fn rows_look_like_hexdump() {
+        assert_eq!(
+            "00000010  7f 45 4c 46 00 ff                                 |.ELF..|",
+            hexdump_row(16, b"\x7fELF\x00\xff")
+        );
+    }
+
+    #[test]
+    fn files_in_sort_order() {
+        let directory =
+            std::env::temp_dir().join(format!("minigrep2-bytes-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let a = directory.join("a.bin").to_string_lossy().into_owned();
+        let b = directory.join("b.bin").to_string_lossy().into_owned();
+        fs::write(&a, b"\x00Z\x00").unwrap();
+        fs::write(&b, b"ZZ\x00").unwrap();
+
+        let run_with = |sort: &str| {
+            let args = ["minigrep2", "--bytes", "-c", sort, "Z", &b, &a];
+            let config = Config::with_settings(
+                args.iter().map(|arg| arg.to_string()),
+                crate::Settings::new(),
+            )
+            .unwrap();
+            let mut out = Vec::new();
+            run(&config, b"Z", &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let by_path = run_with("--sort=path");
+        let by_count = run_with("--sort=count");
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(format!("{}:1\n{}:2\n", a, b), by_path);
+        assert_eq!(format!("{}:2\n{}:1\n", b, a), by_count);
+    }
+
+    #[test]
+    fn matches_in_binary_data() {
+        let mut haystack = vec![0u8; 40];
+        haystack[17..20].copy_from_slice(b"\x00\xffZ");
+        haystack[30] = 0xff;
+
+        let mut out = Vec::new();
+        write_matches("core.dump", &[0xff, b'Z'], &haystack, false, &mut out).unwrap();
+
+        assert_eq!(
+            "\
+core.dump: 0x12
+00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+00000010  00 00 ff 5a 00 00 00 00  00 00 00 00 00 00 ff 00  |...Z............|
+00000020  00 00 00 00 00 00 00 00                           |........|
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,135 @@
+// Reading files that aren't UTF-8.
+//
+// fs::read_to_string refuses anything that isn't valid UTF-8, but logs written on
+// Windows are often UTF-16LE (with a byte order mark) or Latin-1. A file is decoded:
+// - with the encoding its BOM names, if it starts with one (UTF-8, UTF-16LE, UTF-16BE)
+// - otherwise with the --encoding label, if one was given ("utf-16le", "latin1", "shift_jis", ...)
+// - otherwise as UTF-8, as before
+//
+// Searching happens on the decoded UTF-8 text, so for every decoded byte we keep the
+// offset of the original byte it came from, and -b can report positions in the file itself.
+
+use encoding_rs::{CoderResult, Encoding, UTF_8};
+
+pub struct Decoded {
+    pub text: String,
+    // offsets[i] is where the character containing text byte i starts in the original bytes;
+    // one extra entry holds the length of the original. None when the two are the same.
+    pub offsets: Option<Vec<usize>>,
+}
+
+impl Decoded {
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match &self.offsets {
+            Some(offsets) => offsets[offset],
+            None => offset,
+        }
+    }
+}
+
+// encoding_rs knows the labels from the WHATWG Encoding Standard, e.g. "latin1" and "utf-16le"
+pub fn for_label(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown encoding {}", label))
+}
+
+pub fn decode(bytes: Vec<u8>, fallback: Option<&'static Encoding>) -> Result<Decoded, String> {
+    let (encoding, bom_length) = match Encoding::for_bom(&bytes) {
+        Some((encoding, bom_length)) => (encoding, bom_length),
+        None => (fallback.unwrap_or(UTF_8), 0),
+    };
+
+    // Plain UTF-8 needs no conversion and no offset table
+    if encoding == UTF_8 && bom_length == 0 {
+        return match String::from_utf8(bytes) {
+            Ok(text) => Ok(Decoded {
+                text,
+                offsets: None,
+            }),
+            Err(_) => Err(String::from(
+                "stream did not contain valid UTF-8, try --encoding",
+            )),
+        };
+    }
+
+    Ok(decode_with_offsets(encoding, &bytes, bom_length))
+}
+
+// Feeds the decoder one byte at a time. Whenever it produces output, that output
+// belongs to the character that started right after the previous output.
+fn decode_with_offsets(encoding: &'static Encoding, bytes: &[u8], bom_length: usize) -> Decoded {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(bytes.len());
+    let mut offsets = Vec::with_capacity(bytes.len() + 1);
+    let mut char_start = bom_length;
+
+    for index in bom_length..bytes.len() {
+        let last = index + 1 == bytes.len();
+        let before = text.len();
+
+        if let Some(needed) = decoder.max_utf8_buffer_length(1) {
+            text.reserve(needed);
+        }
+        let (result, _, _) = decoder.decode_to_string(&bytes[index..index + 1], &mut text, last);
+        debug_assert_eq!(CoderResult::InputEmpty, result);
+
+        if text.len() > before {
+            offsets.resize(text.len(), char_start);
+            char_start = index + 1;
+        }
+    }
+
+    offsets.push(bytes.len());
+
+    Decoded {
+        text,
+        offsets: Some(offsets),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn utf16_bom_is_detected() {
+        let decoded = decode(utf16le("Rust:\nTrust me."), None).unwrap();
+
+        assert_eq!("Rust:\nTrust me.", decoded.text);
+        // "Trust" starts at text byte 6: the 2 byte BOM plus 6 characters of 2 bytes each
+        assert_eq!(14, decoded.original_offset(6));
+    }
+
+    #[test]
+    fn latin1_by_label() {
+        let latin1 = for_label("latin1").unwrap();
+        let decoded = decode(b"caf\xe9 cr\xe8me".to_vec(), Some(latin1)).unwrap();
+
+        assert_eq!("caf\u{e9} cr\u{e8}me", decoded.text);
+        // "crème" starts at byte 6 in UTF-8 (é takes two bytes) but at byte 5 in the file
+        assert_eq!(5, decoded.original_offset(6));
+        assert_eq!(10, decoded.original_offset(decoded.text.len()));
+    }
+
+    #[test]
+    fn utf8_bom_is_skipped() {
+        let decoded = decode(b"\xef\xbb\xbfPick three.".to_vec(), None).unwrap();
+
+        assert_eq!("Pick three.", decoded.text);
+        assert_eq!(3, decoded.original_offset(0));
+    }
+
+    #[test]
+    fn invalid_utf8_without_label_is_an_error() {
+        assert!(decode(b"caf\xe9".to_vec(), None).is_err());
+        assert!(for_label("klingon").is_err());
+    }
+}
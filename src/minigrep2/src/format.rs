@@ -0,0 +1,321 @@
+// --format: how results are printed.
+//
+// plain     the lines themselves, like grep (the default)
+// vimgrep   path:line:col:text for every match, so `:cexpr system('minigrep2 --format=vimgrep ...')`
+//           in Vim can jump to each one; a line with two matches is printed twice
+// quickfix  path:line:col:text once per matching line, pointing at its first match
+// sarif     a SARIF 2.1.0 JSON report, the format code-scanning dashboards import as "findings"
+//
+// Columns are 1-based. Editors count them in bytes, SARIF (as declared in the report) in characters.
+//
+// Plain output also has what grep has for people reading it: --colour highlights the
+// matches, and -C N prints N lines of context around each selected line, marked with -
+// where selected lines have :, with -- between groups that don't touch.
+
+use crate::{search_lines, table, Colour, Config, Input, Match, Searcher};
+use serde_json::json;
+use std::io::{self, Write};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Plain,
+    Vimgrep,
+    Quickfix,
+    Sarif,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "plain" => Ok(Format::Plain),
+            "vimgrep" => Ok(Format::Vimgrep),
+            "quickfix" => Ok(Format::Quickfix),
+            "sarif" => Ok(Format::Sarif),
+            _ => Err(format!(
+                "Unknown format {}, use plain, vimgrep, quickfix or sarif",
+                name
+            )),
+        }
+    }
+}
+
+pub fn write_results<W: Write>(config: &Config, sources: &[Input], out: &mut W) -> io::Result<()> {
+    if let Some(table) = config.table {
+        return table::write_records(config, table, sources, out);
+    }
+
+    match config.format {
+        Format::Plain => write_plain(config, sources, out),
+        Format::Vimgrep => write_editor(config, sources, out, true),
+        Format::Quickfix => write_editor(config, sources, out, false),
+        Format::Sarif => write_sarif(config, sources, out),
+    }
+}
+
+// Start and end of a highlighted match, bold red like grep's
+const HIGHLIGHT: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+// What write_plain does with each line of a file
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Hidden,
+    Context,
+    // the first line of the selected line or paragraph with this index
+    Selected(usize),
+    // the other lines of a selected paragraph, printed with its first one
+    Covered,
+}
+
+fn write_plain<W: Write>(config: &Config, sources: &[Input], out: &mut W) -> io::Result<()> {
+    // Like grep, only say where a line came from when there is more than one place it could come from.
+    let show_names = sources.len() > 1 || sources.iter().any(|input| input.name.contains('!'));
+    let searcher = config.searcher();
+    // whether a group of lines has been printed, which the next one is separated from
+    let mut printed = false;
+
+    for input in sources {
+        let results = search_lines(config, &input.contents);
+
+        if config.count {
+            let prefix = if show_names {
+                format!("{}:", input.name)
+            } else {
+                String::new()
+            };
+            writeln!(out, "{}{}", prefix, results.len())?;
+            continue;
+        }
+
+        if config.context == 0 {
+            for found in &results {
+                let prefix = prefix(
+                    config,
+                    input,
+                    show_names,
+                    found.line_number,
+                    found.byte_offset,
+                    ':',
+                );
+                writeln!(out, "{}{}", prefix, selected_text(config, &searcher, found))?;
+            }
+            continue;
+        }
+
+        // Which lines to print: the selected ones and the context around them
+        let lines = numbered_lines(&input.contents);
+        let mut rows = vec![Row::Hidden; lines.len()];
+        for (index, found) in results.iter().enumerate() {
+            let first = found.line_number - 1;
+            let last = (first + found.line.lines().count().max(1) - 1).min(lines.len() - 1);
+            let after = (last + config.context).min(lines.len() - 1);
+            for row in &mut rows[first.saturating_sub(config.context)..=after] {
+                if *row == Row::Hidden {
+                    *row = Row::Context;
+                }
+            }
+            rows[first] = Row::Selected(index);
+            for row in &mut rows[first + 1..=last] {
+                *row = Row::Covered;
+            }
+        }
+
+        let mut previous = None;
+        for (number, row) in rows.iter().enumerate() {
+            let (offset, separator, text) = match *row {
+                Row::Hidden => continue,
+                Row::Covered => {
+                    previous = Some(number);
+                    continue;
+                }
+                Row::Selected(index) => {
+                    let found = &results[index];
+                    (
+                        found.byte_offset,
+                        ':',
+                        selected_text(config, &searcher, found),
+                    )
+                }
+                Row::Context => (lines[number].0, '-', lines[number].1.to_string()),
+            };
+
+            if printed && previous.is_none_or(|at| at + 1 != number) {
+                writeln!(out, "--")?;
+            }
+            previous = Some(number);
+            printed = true;
+
+            let prefix = prefix(config, input, show_names, number + 1, offset, separator);
+            writeln!(out, "{}{}", prefix, text)?;
+        }
+    }
+
+    Ok(())
+}
+
+// What goes before a line: where it came from, each part followed by : for a selected
+// line and - for context
+fn prefix(
+    config: &Config,
+    input: &Input,
+    show_names: bool,
+    line_number: usize,
+    offset: usize,
+    separator: char,
+) -> String {
+    let mut prefix = String::new();
+    if show_names {
+        prefix.push_str(&format!("{}{}", input.name, separator));
+    }
+    if config.line_number {
+        prefix.push_str(&format!("{}{}", line_number, separator));
+    }
+    if config.byte_offset {
+        prefix.push_str(&format!("{}{}", input.original_offset(offset), separator));
+    }
+    prefix
+}
+
+fn selected_text(config: &Config, searcher: &Searcher, found: &Match) -> String {
+    if config.colour == Colour::Always {
+        highlight(config, searcher, found.line)
+    } else {
+        found.line.to_string()
+    }
+}
+
+// Every line of contents with the offset it starts at, split like str::lines
+fn numbered_lines(contents: &str) -> Vec<(usize, &str)> {
+    let mut offset = 0;
+    contents
+        .split_inclusive('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            (start, line.strip_suffix('\r').unwrap_or(line))
+        })
+        .collect()
+}
+
+// The line with its matches highlighted. A --query or -v selects the whole line, which
+// has nothing in it to point at.
+fn highlight(config: &Config, searcher: &Searcher, line: &str) -> String {
+    if config.query_language.is_some() || config.invert_match {
+        return line.to_string();
+    }
+
+    let mut text = String::new();
+    let mut end = 0;
+    for span in searcher.find_all(line) {
+        text.push_str(&line[end..span.start]);
+        text.push_str(HIGHLIGHT);
+        text.push_str(&line[span.clone()]);
+        text.push_str(RESET);
+        end = span.end;
+    }
+    text.push_str(&line[end..]);
+    text
+}
+
+fn write_editor<W: Write>(
+    config: &Config,
+    sources: &[Input],
+    out: &mut W,
+    every_match: bool,
+) -> io::Result<()> {
+    let searcher = config.searcher();
+    for input in sources {
+        for found in search_lines(config, &input.contents) {
+            let line = first_line(&found);
+            for span in spans(config, &searcher, line, every_match) {
+                writeln!(
+                    out,
+                    "{}:{}:{}:{}",
+                    input.name,
+                    found.line_number,
+                    span.start + 1,
+                    line
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_sarif<W: Write>(config: &Config, sources: &[Input], out: &mut W) -> io::Result<()> {
+    let message = if config.invert_match {
+        format!("Line does not match `{}`", config.query)
+    } else {
+        format!("Line matches `{}`", config.query)
+    };
+
+    let searcher = config.searcher();
+    let mut results = Vec::new();
+    for input in sources {
+        for found in search_lines(config, &input.contents) {
+            let line = first_line(&found);
+            for span in spans(config, &searcher, line, true) {
+                results.push(json!({
+                    "ruleId": "match",
+                    "level": "note",
+                    "message": { "text": message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": input.name },
+                            "region": {
+                                "startLine": found.line_number,
+                                "startColumn": line[..span.start].chars().count() + 1,
+                                "endColumn": line[..span.end].chars().count() + 1,
+                                "snippet": { "text": line }
+                            }
+                        }
+                    }]
+                }));
+            }
+        }
+    }
+
+    let report = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "minigrep2",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": "match",
+                        "shortDescription": { "text": "A line selected by the search query" }
+                    }]
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results
+        }]
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)
+}
+
+// A paragraph (--scope paragraph) spans several lines; editors get its first one.
+fn first_line<'a>(found: &Match<'a>) -> &'a str {
+    found.line.lines().next().unwrap_or("")
+}
+
+// Where in the line to point the editor. A --query or -v selects a whole line, so that
+// points at its start; a plain search points at the matches themselves.
+fn spans(config: &Config, searcher: &Searcher, line: &str, every_match: bool) -> Vec<Range<usize>> {
+    if config.query_language.is_some() || config.invert_match {
+        return vec![Range { start: 0, end: 0 }];
+    }
+
+    if every_match {
+        searcher.find_all(line)
+    } else {
+        searcher.find(line).into_iter().collect()
+    }
+}
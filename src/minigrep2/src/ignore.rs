@@ -0,0 +1,105 @@
+// --ignore GLOB and `ignore =` in the config file: what a directory search skips.
+//
+// *  any run of characters except /
+// ?  any one character except /
+//
+// A glob without a / is matched against the name of every file and directory on the
+// way down, so `target` skips each target directory and `*.min.js` each minified file.
+// A glob with a / is matched against the path below the directory being searched, so
+// `minigrep2 -i todo . --ignore 'src/generated/*'` skips ./src/generated/parser.rs.
+//
+// Files named on the command line are always searched.
+
+use std::path::Path;
+
+// relative is the path below the directory named on the command line
+pub fn is_ignored(relative: &Path, globs: &[String]) -> bool {
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let relative = relative.to_string_lossy();
+
+    globs.iter().any(|glob| {
+        if glob.contains('/') {
+            matches(glob.trim_start_matches("./"), &relative)
+        } else {
+            matches(glob, &name)
+        }
+    })
+}
+
+pub fn matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut g, mut t) = (0, 0);
+    // the last * seen and where in text it started matching, to go back to on a mismatch
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some('?') if text[t] != '/' => {
+                g += 1;
+                t += 1;
+            }
+            Some(&c) if c != '*' && c != '?' && c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            // let the * take one more character, unless that is a /
+            _ => match star {
+                Some((star_g, star_t)) if text[star_t] != '/' => {
+                    g = star_g + 1;
+                    t = star_t + 1;
+                    star = Some((star_g, t));
+                }
+                _ => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globs(globs: &[&str]) -> Vec<String> {
+        globs.iter().map(|glob| String::from(*glob)).collect()
+    }
+
+    #[test]
+    fn globs_match_like_a_shell() {
+        assert!(matches("*.min.js", "app.min.js"));
+        assert!(!matches("*.min.js", "app.js"));
+        assert!(matches("?.txt", "a.txt"));
+        assert!(!matches("?.txt", "ab.txt"));
+        assert!(matches("target", "target"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("src/*", "src/generated/parser.rs"));
+        assert!(matches("src/*/*", "src/generated/parser.rs"));
+    }
+
+    #[test]
+    fn names_and_paths() {
+        let ignore = globs(&["target", "*.lock", "src/generated/*"]);
+
+        assert!(is_ignored(Path::new("target"), &ignore));
+        assert!(is_ignored(Path::new("crates/cli/target"), &ignore));
+        assert!(is_ignored(Path::new("Cargo.lock"), &ignore));
+        assert!(is_ignored(Path::new("src/generated/parser.rs"), &ignore));
+        assert!(!is_ignored(
+            Path::new("lib/src/generated/parser.rs"),
+            &ignore
+        ));
+        assert!(!is_ignored(Path::new("src/main.rs"), &ignore));
+        assert!(!is_ignored(Path::new("src/main.rs"), &[]));
+    }
+}
@@ -0,0 +1,383 @@
+// --interactive: a full-screen picker.
+//
+// The files are loaded once. As you type, the query is run again over all of them,
+// but only after you stop typing for DEBOUNCE, so a fast typist doesn't trigger a
+// search on every key. Up/Down move through the matches, the lower half of the screen
+// shows the selected line with some context, Enter prints `path:line` and exits, and
+// Esc or Ctrl-C exits without printing anything.
+//
+// The Picker holds all the state and knows nothing about the terminal, so it can be tested;
+// `run` is the thin loop that reads keys and draws it.
+
+use crate::{Config, Input, SearcherBuilder};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::error::Error;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
+
+// Lines shown above and below the selected match in the preview pane
+pub const CONTEXT: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    // index into the loaded sources
+    pub source: usize,
+    pub line_number: usize,
+}
+
+pub struct Picker {
+    pub query: String,
+    pub hits: Vec<Hit>,
+    pub selected: usize,
+    // When the query last changed and hasn't been searched for yet
+    edited_at: Option<Instant>,
+}
+
+impl Picker {
+    pub fn new() -> Picker {
+        Picker {
+            query: String::new(),
+            hits: Vec::new(),
+            selected: 0,
+            edited_at: None,
+        }
+    }
+
+    pub fn type_char(&mut self, c: char, now: Instant) {
+        self.query.push(c);
+        self.edited_at = Some(now);
+    }
+
+    pub fn backspace(&mut self, now: Instant) {
+        if self.query.pop().is_some() {
+            self.edited_at = Some(now);
+        }
+    }
+
+    // True once the query has been left alone for DEBOUNCE
+    pub fn is_due(&self, now: Instant) -> bool {
+        match self.edited_at {
+            Some(edited_at) => now.duration_since(edited_at) >= DEBOUNCE,
+            None => false,
+        }
+    }
+
+    pub fn refresh(&mut self, options: SearcherBuilder, sources: &[Input]) {
+        let searcher = options.build(&self.query);
+
+        self.hits = sources
+            .iter()
+            .enumerate()
+            .flat_map(|(source, input)| {
+                searcher.matches(&input.contents).map(move |found| Hit {
+                    source,
+                    line_number: found.line_number,
+                })
+            })
+            .collect();
+        self.selected = 0;
+        self.edited_at = None;
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.hits.len() {
+            self.selected += 1;
+        }
+    }
+
+    // What Enter prints
+    pub fn selection(&self, sources: &[Input]) -> Option<String> {
+        self.hits
+            .get(self.selected)
+            .map(|hit| format!("{}:{}", sources[hit.source].name, hit.line_number))
+    }
+
+    // The selected line and CONTEXT lines on either side, with their line numbers
+    pub fn preview<'a>(&self, sources: &'a [Input]) -> Vec<(usize, &'a str)> {
+        let hit = match self.hits.get(self.selected) {
+            Some(hit) => hit,
+            None => return Vec::new(),
+        };
+
+        let first = hit.line_number.saturating_sub(CONTEXT).max(1);
+        sources[hit.source]
+            .contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .skip(first - 1)
+            .take(hit.line_number + CONTEXT + 1 - first)
+            .collect()
+    }
+}
+
+impl Default for Picker {
+    fn default() -> Picker {
+        Picker::new()
+    }
+}
+
+// Puts the terminal back the way we found it, even if drawing fails half way
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<RawTerminal> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen, Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(config: &Config, sources: &[Input]) -> Result<(), Box<dyn Error>> {
+    let options = config.searcher_builder();
+    let mut picker = Picker::new();
+    picker.refresh(options, sources);
+
+    // The screen is drawn on stderr, so stdout only ever gets the chosen `path:line`
+    // and `vim $(minigrep2 --interactive src/*.rs)` style pipelines keep working.
+    let chosen = {
+        let _terminal = RawTerminal::enter()?;
+        let mut screen = io::stderr();
+
+        // Only redraw when something changed, otherwise the screen flickers
+        let mut redraw = true;
+
+        loop {
+            if redraw {
+                draw(&mut screen, &picker, sources)?;
+                redraw = false;
+            }
+
+            if !event::poll(Duration::from_millis(50))? {
+                if picker.is_due(Instant::now()) {
+                    picker.refresh(options, sources);
+                    redraw = true;
+                }
+                continue;
+            }
+
+            match event::read()? {
+                Event::Resize(_, _) => redraw = true,
+                Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => {
+                    redraw = true;
+                    match code {
+                        KeyCode::Esc => break None,
+                        KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                            break None
+                        }
+                        KeyCode::Enter => {
+                            // Don't pick from a stale list if Enter comes before the debounce did
+                            if picker.edited_at.is_some() {
+                                picker.refresh(options, sources);
+                            }
+                            break picker.selection(sources);
+                        }
+                        KeyCode::Up => picker.up(),
+                        KeyCode::Down => picker.down(),
+                        KeyCode::Backspace => picker.backspace(Instant::now()),
+                        KeyCode::Char(c) => picker.type_char(c, Instant::now()),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+
+    if let Some(chosen) = chosen {
+        println!("{}", chosen);
+    }
+
+    Ok(())
+}
+
+fn draw<W: Write>(out: &mut W, picker: &Picker, sources: &[Input]) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    let width = width as usize;
+    let height = height as usize;
+
+    // Top half: the query and the matches. Bottom half: the preview.
+    let list_rows = (height / 2).saturating_sub(2).max(1);
+    let first_shown = picker.selected.saturating_sub(list_rows - 1);
+
+    queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+    queue!(
+        out,
+        Print(fit(
+            &format!("> {}  ({} matches)", picker.query, picker.hits.len()),
+            width
+        ))
+    )?;
+
+    for (row, hit) in picker
+        .hits
+        .iter()
+        .enumerate()
+        .skip(first_shown)
+        .take(list_rows)
+    {
+        let input = &sources[hit.source];
+        let line = input
+            .contents
+            .lines()
+            .nth(hit.line_number - 1)
+            .unwrap_or("");
+        let text = fit(
+            &format!("{}:{}: {}", input.name, hit.line_number, line.trim()),
+            width,
+        );
+
+        queue!(out, MoveTo(0, (1 + row - first_shown) as u16))?;
+        if row == picker.selected {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(text),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(text))?;
+        }
+    }
+
+    let preview_top = list_rows + 1;
+    queue!(out, MoveTo(0, preview_top as u16), Print("-".repeat(width)))?;
+
+    let selected_line = picker.hits.get(picker.selected).map(|hit| hit.line_number);
+    for (row, (number, line)) in picker.preview(sources).into_iter().enumerate() {
+        if preview_top + 1 + row >= height {
+            break;
+        }
+        let marker = if Some(number) == selected_line {
+            '>'
+        } else {
+            ' '
+        };
+        queue!(
+            out,
+            MoveTo(0, (preview_top + 1 + row) as u16),
+            Print(fit(&format!("{}{:>5} {}", marker, number, line), width))
+        )?;
+    }
+
+    out.flush()
+}
+
+// Cuts text to the terminal width, counting characters rather than bytes
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXACT: SearcherBuilder = SearcherBuilder::new();
+
+    fn sources() -> Vec<Input> {
+        vec![
+            Input::new(
+                String::from("poem.txt"),
+                String::from("Rust:\nsafe, fast, productive.\nPick three.\nTrust me."),
+            ),
+            Input::new(
+                String::from("notes.txt"),
+                String::from("fast food\nslow food"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn search_waits_for_the_debounce() {
+        let start = Instant::now();
+        let mut picker = Picker::new();
+
+        picker.type_char('f', start);
+        picker.type_char('a', start + Duration::from_millis(100));
+
+        assert!(!picker.is_due(start + Duration::from_millis(200)));
+        assert!(picker.is_due(start + Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn refresh_searches_every_source() {
+        let sources = sources();
+        let mut picker = Picker::new();
+        picker.query = String::from("fast");
+
+        picker.refresh(EXACT, &sources);
+
+        assert_eq!(
+            vec![
+                Hit {
+                    source: 0,
+                    line_number: 2
+                },
+                Hit {
+                    source: 1,
+                    line_number: 1
+                }
+            ],
+            picker.hits
+        );
+        assert!(!picker.is_due(Instant::now() + DEBOUNCE));
+    }
+
+    #[test]
+    fn selection_moves_within_the_matches() {
+        let sources = sources();
+        let mut picker = Picker::new();
+        picker.query = String::from("fast");
+        picker.refresh(EXACT, &sources);
+
+        picker.up();
+        assert_eq!(Some(String::from("poem.txt:2")), picker.selection(&sources));
+
+        picker.down();
+        picker.down();
+        assert_eq!(
+            Some(String::from("notes.txt:1")),
+            picker.selection(&sources)
+        );
+    }
+
+    #[test]
+    fn preview_shows_context_around_the_selection() {
+        let sources = sources();
+        let mut picker = Picker::new();
+        picker.query = String::from("Trust");
+        picker.refresh(EXACT, &sources);
+
+        assert_eq!(
+            vec![
+                (1, "Rust:"),
+                (2, "safe, fast, productive."),
+                (3, "Pick three."),
+                (4, "Trust me.")
+            ],
+            picker.preview(&sources)
+        );
+    }
+}
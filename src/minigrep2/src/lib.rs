@@ -1,13 +1,81 @@
 // Improved version using iterator
 
-use std::env;
 use std::error::Error;
 use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+mod archive;
+mod bytes;
+mod encoding;
+mod format;
+mod ignore;
+mod interactive;
+mod mapped;
+mod order;
+mod rank;
+mod settings;
+mod table;
+
+pub use crate::format::{write_results, Format};
+pub use crate::mapped::Contents;
+pub use crate::order::Sort;
+pub use crate::settings::{Colour, Setting, Settings, Source};
+pub use crate::table::Table;
+pub use grep_core::{
+    find_lines, search, search_case_insensitive, Folding, Match, Query, Scope, Searcher,
+    SearcherBuilder,
+};
 
 pub struct Config {
+    // the first argument, or -e PATTERN for one that starts with -
     pub query: String,
-    pub filename: String,
+    // one or more files to search
+    pub filenames: Vec<String>,
     pub case_sensitive: bool,
+    // -v / --invert-match: select the lines that do NOT contain the query
+    pub invert_match: bool,
+    // -n / --line-number: prefix each printed line with its 1-based line number
+    pub line_number: bool,
+    // -c / --count: only print how many lines were selected
+    pub count: bool,
+    // --search-archives: also search the text files inside .tar, .tar.gz and .zip files
+    pub search_archives: bool,
+    // --ignore-diacritics: "cafe" matches "café" and "CAFÉ"
+    pub ignore_diacritics: bool,
+    // -b / --byte-offset: print where in the file each match starts
+    pub byte_offset: bool,
+    // --interactive: type the query in a full-screen picker instead of on the command line
+    pub interactive: bool,
+    // --rank: list the files by BM25 relevance to the query's words, best first
+    pub rank: bool,
+    // --query 'timeout AND (retry OR backoff) NOT test': a boolean query instead of a plain string
+    pub query_language: Option<Query>,
+    // --scope line|paragraph: what a --query is checked against
+    pub scope: Scope,
+    // --encoding LABEL: how to decode files that don't start with a byte order mark
+    pub encoding: Option<&'static encoding_rs::Encoding>,
+    // --format plain|vimgrep|quickfix|sarif
+    pub format: Format,
+    // --csv / --tsv: search the records of a table instead of lines
+    pub table: Option<Table>,
+    // --field NAME|N: only match against this column of the table
+    pub field: Option<String>,
+    // --sort path|mtime|count: search the files in this order
+    pub sort: Option<Sort>,
+    // --unique: print each distinct matching line once, with how often it was found
+    pub unique: bool,
+    // --bytes: search the raw bytes of the files for this pattern, the query with its \xHH escapes decoded
+    pub bytes: Option<Vec<u8>>,
+    // --ignore GLOB: skip the files and directories it matches when searching a directory
+    pub ignore: Vec<String>,
+    // --colour auto|always|never: highlight the matches in plain output
+    pub colour: Colour,
+    // -C N / --context N: also print the N lines before and after each selected line
+    pub context: usize,
+    // --debug-config: print the merged settings and where each came from, then exit
+    pub debug_config: bool,
+    pub settings: Settings,
 }
 
 impl Config {
@@ -17,7 +85,24 @@ impl Config {
     // the env::args function shows that the type of the iterator it returns is std::env::Args
     // we’re taking ownership of args and we’ll be mutating args by iterating over it
     // With the change to args, the lifetime elision rules no longer apply, and we must specify the 'static lifetime.
-    pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
+    // Version 2: pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
+
+    // Accepting any iterator of Strings instead of env::Args lets the tests build a Config from a plain Vec.
+    // main still passes env::args(), which is such an iterator.
+    // The error is a String now, because config file problems need to name the file and line.
+    pub fn new<T>(args: T) -> Result<Config, String>
+    where
+        T: Iterator<Item = String>,
+    {
+        Config::with_settings(args, Settings::load()?)
+    }
+
+    // Command-line flags are applied on top of settings that already hold the defaults,
+    // the config file and the environment, so a flag always wins.
+    pub fn with_settings<T>(mut args: T, mut settings: Settings) -> Result<Config, String>
+    where
+        T: Iterator<Item = String>,
+    {
         /**
         Version 1:
         if args.len() < 3 {
@@ -34,119 +119,878 @@ impl Config {
         // We want to ignore that and get to the next value
         args.next();
 
+        // Options may appear anywhere on the command line; everything else is a positional argument.
+        // The --no-* forms switch off something the config file turned on.
+        let mut debug_config = false;
+        let mut interactive = false;
+        let mut rank = false;
+        let mut query_text = None;
+        let mut scope = Scope::Line;
+        let mut encoding = None;
+        let mut format = Format::Plain;
+        let mut table = None;
+        let mut field = None;
+        let mut sort = None;
+        let mut unique = false;
+        let mut raw_bytes = false;
+        let mut pattern = None;
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            // Like grep, everything after -- is the query and files, even if it starts with -
+            if arg == "--" {
+                positional.extend(&mut args);
+                break;
+            }
+
+            // --name=value is the same as --name value
+            let (name, inline_value) = match arg.find('=') {
+                Some(at) if arg.starts_with("--") => {
+                    (arg[..at].to_string(), Some(arg[at + 1..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+
+            let (key, value) = match name.as_str() {
+                "-i" | "--ignore-case" => ("ignore_case", true),
+                "-s" | "--case-sensitive" => ("ignore_case", false),
+                "-v" | "--invert-match" => ("invert_match", true),
+                "--no-invert-match" => ("invert_match", false),
+                "-n" | "--line-number" => ("line_number", true),
+                "--no-line-number" => ("line_number", false),
+                "-c" | "--count" => ("count", true),
+                "--no-count" => ("count", false),
+                "--search-archives" => ("search_archives", true),
+                "--no-search-archives" => ("search_archives", false),
+                "--ignore-diacritics" => ("ignore_diacritics", true),
+                "--no-ignore-diacritics" => ("ignore_diacritics", false),
+                "-b" | "--byte-offset" => ("byte_offset", true),
+                "--no-byte-offset" => ("byte_offset", false),
+                "--ignore" => {
+                    let glob = option_value(&name, inline_value, &mut args)?;
+                    settings.add_ignore(glob, Source::CommandLine);
+                    continue;
+                }
+                "--no-ignore" => {
+                    settings.set_value("ignore", "", Source::CommandLine)?;
+                    continue;
+                }
+                "--colour" | "--color" => {
+                    let when = option_value(&name, inline_value, &mut args)?;
+                    settings.set_value("colour", &when, Source::CommandLine)?;
+                    continue;
+                }
+                "-C" | "--context" => {
+                    let lines = option_value(&name, inline_value, &mut args)?;
+                    settings.set_value("context", &lines, Source::CommandLine)?;
+                    continue;
+                }
+                "--debug-config" => {
+                    debug_config = true;
+                    continue;
+                }
+                "--interactive" => {
+                    interactive = true;
+                    continue;
+                }
+                "--rank" => {
+                    rank = true;
+                    continue;
+                }
+                // -e -v searches for "-v"
+                "-e" | "--pattern" => {
+                    pattern = Some(option_value(&name, inline_value, &mut args)?);
+                    continue;
+                }
+                "--query" => {
+                    query_text = Some(option_value(&name, inline_value, &mut args)?);
+                    continue;
+                }
+                "--encoding" => {
+                    let label = option_value(&name, inline_value, &mut args)?;
+                    encoding = Some(encoding::for_label(&label)?);
+                    continue;
+                }
+                "--format" => {
+                    format = Format::parse(&option_value(&name, inline_value, &mut args)?)?;
+                    continue;
+                }
+                "--csv" => {
+                    table = Some(Table::Csv);
+                    continue;
+                }
+                "--tsv" => {
+                    table = Some(Table::Tsv);
+                    continue;
+                }
+                "--field" => {
+                    field = Some(option_value(&name, inline_value, &mut args)?);
+                    continue;
+                }
+                "--sort" => {
+                    sort = Some(Sort::parse(&option_value(&name, inline_value, &mut args)?)?);
+                    continue;
+                }
+                "--unique" => {
+                    unique = true;
+                    continue;
+                }
+                "--bytes" => {
+                    raw_bytes = true;
+                    continue;
+                }
+                "--scope" => {
+                    scope = match option_value(&name, inline_value, &mut args)?.as_str() {
+                        "line" => Scope::Line,
+                        "paragraph" => Scope::Paragraph,
+                        other => {
+                            return Err(format!("Unknown scope {}, use line or paragraph", other))
+                        }
+                    };
+                    continue;
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!(
+                        "Unknown option {}, to search for it use -e {} or put it after --",
+                        arg, arg
+                    ))
+                }
+                _ => {
+                    positional.push(arg);
+                    continue;
+                }
+            };
+
+            if inline_value.is_some() {
+                return Err(format!("{} doesn't take a value", name));
+            }
+            settings.set(key, value, Source::CommandLine)?;
+        }
+
+        // Ranking scores files by the words of the query; AND, OR and NEAR have no score
+        if rank && query_text.is_some() {
+            return Err(String::from("--rank takes a plain query, not a --query"));
+        }
+
+        // The picker searches for what is typed and shows plain lines, so these would do nothing
+        if interactive
+            && (pattern.is_some()
+                || query_text.is_some()
+                || rank
+                || table.is_some()
+                || format != Format::Plain)
+        {
+            return Err(String::from(
+                "--interactive can't be combined with -e, --query, --rank, --csv, --tsv or --format",
+            ));
+        }
+        if pattern.is_some() && query_text.is_some() {
+            return Err(String::from("-e and --query both give the query, use one"));
+        }
+
+        if field.is_some() && table.is_none() {
+            return Err(String::from("--field needs --csv or --tsv"));
+        }
+        if table.is_some() && format != Format::Plain {
+            return Err(String::from("--csv and --tsv print records, not --format"));
+        }
+
+        if unique && (table.is_some() || format != Format::Plain) {
+            return Err(String::from("--unique only prints plain lines"));
+        }
+
+        if raw_bytes
+            && (query_text.is_some()
+                || interactive
+                || rank
+                || unique
+                || table.is_some()
+                || format != Format::Plain
+                || settings.invert_match.value)
+        {
+            return Err(String::from(
+                "--bytes can't be combined with --query, --interactive, --rank, --unique, --csv, --tsv, --format or -v",
+            ));
+        }
+
+        let query_language = match &query_text {
+            Some(text) => Some(Query::parse(text)?),
+            None => None,
+        };
+
+        let mut positional = positional.into_iter();
+        // In --interactive mode the query is typed into the picker, and an -e or a --query
+        // has already been given, so in all these cases every argument is a file.
+        let query = if interactive {
+            Some(String::new())
+        } else if pattern.is_some() {
+            pattern
+        } else if query_text.is_some() {
+            query_text
+        } else {
+            positional.next()
+        };
+        let filenames: Vec<String> = positional.collect();
+
         // If next returns a Some, we use a match to extract the value.
         // If it returns None, it means not enough arguments were given and we return early with an Err value.
-        let query = match args.next() {
+        // --debug-config does not search anything, so it doesn't need them.
+        let query = match query {
             Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
+            None if debug_config => String::new(),
+            None => return Err(String::from("Didn't get a query string")),
         };
 
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file name"),
-        };
+        if filenames.is_empty() && !debug_config {
+            return Err(String::from("Didn't get a file name"));
+        }
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        let bytes = if raw_bytes {
+            Some(bytes::parse_pattern(&query)?)
+        } else {
+            None
+        };
 
         Ok(Config {
             query,
-            filename,
-            case_sensitive,
+            filenames,
+            case_sensitive: !settings.ignore_case.value,
+            invert_match: settings.invert_match.value,
+            line_number: settings.line_number.value,
+            count: settings.count.value,
+            search_archives: settings.search_archives.value,
+            ignore_diacritics: settings.ignore_diacritics.value,
+            byte_offset: settings.byte_offset.value,
+            ignore: settings.ignore.value.clone(),
+            colour: settings.colour.value,
+            context: settings.context.value,
+            interactive,
+            rank,
+            query_language,
+            scope,
+            encoding,
+            format,
+            table,
+            field,
+            sort,
+            unique,
+            bytes,
+            debug_config,
+            settings,
         })
     }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+// The value of an option like --query: either after an = or the next argument
+fn option_value<T>(name: &str, inline_value: Option<String>, args: &mut T) -> Result<String, String>
+where
+    T: Iterator<Item = String>,
+{
+    match inline_value {
+        Some(value) => Ok(value),
+        None => args.next().ok_or_else(|| format!("{} needs a value", name)),
+    }
+}
+
+impl Config {
+    // How the query and each line are normalized before they are compared
+    pub fn folding(&self) -> Folding {
+        self.searcher_builder().folding()
+    }
+
+    // The search options from the command line, for any pattern
+    pub fn searcher_builder(&self) -> SearcherBuilder {
+        SearcherBuilder::new()
+            .ignore_case(!self.case_sensitive)
+            .ignore_diacritics(self.ignore_diacritics)
+            .invert_match(self.invert_match)
+            .scope(self.scope)
+    }
+
+    // The search the command line asks for: the --query if there is one, otherwise the query string
+    pub fn searcher(&self) -> Searcher {
+        match &self.query_language {
+            Some(query) => self.searcher_builder().build_query(query.clone()),
+            None => self.searcher_builder().build(&self.query),
+        }
+    }
+}
+
+pub fn run(mut config: Config) -> Result<(), Box<dyn Error>> {
+    // auto means colour when a person reads the output, not a pipe or a file
+    if config.colour == Colour::Auto {
+        config.colour = if io::stdout().is_terminal() {
+            Colour::Always
+        } else {
+            Colour::Never
+        };
+    }
+
+    if let Some(pattern) = &config.bytes {
+        return bytes::run(&config, pattern, &mut io::stdout().lock());
+    }
 
     // ? will return the error value from the current function for the caller to handle.
-    let contents = fs::read_to_string(config.filename)?;
+    let mut sources = load_sources(&config)?;
+    if let Some(sort) = config.sort {
+        order::sort_sources(&config, sort, &mut sources);
+    }
 
-    let results = if config.case_sensitive {
-        search(&config.query, &contents)
-    } else {
-        search_case_insensitive(&config.query, &contents)
-    };
+    if config.interactive {
+        return interactive::run(&config, &sources);
+    }
 
-    for line in results {
-        println!("{}", line);
+    let stdout = io::stdout();
+    if config.rank {
+        rank::write_ranked(
+            &config.query,
+            config.folding(),
+            &sources,
+            &mut stdout.lock(),
+        )?;
+    } else if config.unique {
+        order::write_unique(&config, &sources, &mut stdout.lock())?;
+    } else {
+        write_results(&config, &sources, &mut stdout.lock())?;
     }
 
     Ok(())
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    /**
-    Version 1:
-    let mut results = Vec::new();
+// Something to search: a file, or an entry inside an archive named archive.zip!inner.txt
+pub struct Input {
+    pub name: String,
+    // always UTF-8, whatever the file was written in
+    pub contents: Contents,
+    decoded: Option<encoding::Decoded>,
+}
 
-    for line in contents.lines() {
-        if line.contains(query) {
-            results.push(line);
+impl Input {
+    pub fn new(name: String, contents: String) -> Input {
+        Input {
+            name,
+            contents: Contents::from(contents),
+            decoded: None,
         }
     }
 
-    results
-    **/
-
-    // Using iterator adaptor methods in the implementation of the search function
-    contents
-        .lines()
-        .filter(|line| line.contains(query))
-        .collect()
+    // Turns an offset in contents into an offset in the file as it is on disk
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match &self.decoded {
+            Some(decoded) => decoded.original_offset(offset),
+            None => offset,
+        }
+    }
 }
 
-// CASE_INSENSITIVE=1 cargo run to poem.txt
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    /**
-    Version 1:
-    let query = query.to_lowercase();
-    let mut results = Vec::new();
+pub fn load_sources(config: &Config) -> Result<Vec<Input>, Box<dyn Error>> {
+    let mut sources = Vec::new();
+
+    for filename in &expand(&config.filenames, &config.ignore)? {
+        if config.search_archives && archive::is_archive(filename) {
+            // A broken archive, or one too big to unpack, shouldn't stop the other files being searched
+            let entries = archive::read_entries(filename).unwrap_or_else(|e| {
+                eprintln!("{}: skipped, {}", filename, e);
+                Vec::new()
+            });
+            for entry in entries {
+                sources.push(Input::new(
+                    format!("{}!{}", filename, entry.name),
+                    entry.contents,
+                ));
+            }
+            continue;
+        }
 
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
+        // Big UTF-8 files are searched where they are, without copying them into memory
+        let file = fs::File::open(filename)?;
+        if config.encoding.is_none() && file.metadata()?.len() >= mapped::MAP_THRESHOLD {
+            if let Some(contents) = Contents::map(&file)? {
+                sources.push(Input {
+                    name: filename.clone(),
+                    contents,
+                    decoded: None,
+                });
+                continue;
+            }
         }
+
+        // fs::read rather than fs::read_to_string, so UTF-16 and Latin-1 files can be decoded
+        let mut decoded = match encoding::decode(fs::read(filename)?, config.encoding) {
+            Ok(decoded) => decoded,
+            // Like grep, a binary file found in a directory is passed over with a note;
+            // one named on the command line was asked for, so it is an error.
+            Err(e) if !config.filenames.contains(filename) => {
+                eprintln!("{}: skipped, {}", filename, e);
+                continue;
+            }
+            Err(e) => return Err(format!("{}: {}", filename, e).into()),
+        };
+        let contents = std::mem::take(&mut decoded.text);
+        sources.push(Input {
+            name: filename.clone(),
+            contents: Contents::from(contents),
+            decoded: Some(decoded),
+        });
     }
 
-    results
-    **/
+    Ok(sources)
+}
+
+// Directories are searched recursively, leaving out what the ignore globs match. Their
+// entries come in whatever order the filesystem lists them; --sort makes that order fixed.
+fn expand(filenames: &[String], ignore: &[String]) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for filename in filenames {
+        let path = Path::new(filename);
+        if path.is_dir() {
+            walk(path, path, ignore, &mut files)?;
+        } else {
+            files.push(filename.clone());
+        }
+    }
+    Ok(files)
+}
 
-    // Using iterator adaptor methods in the implementation of the search function
-    let query = query.to_lowercase();
-    contents
-        .lines()
-        .filter(|line| line.to_lowercase().contains(&query))
-        .collect()
+// Links to directories are not followed, so a link back up the tree can't loop forever
+fn walk(
+    root: &Path,
+    directory: &Path,
+    ignore: &[String],
+    files: &mut Vec<String>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        if ignore::is_ignored(path.strip_prefix(root).unwrap_or(&path), ignore) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            walk(root, &path, ignore, files)?;
+        } else if path.is_file() {
+            files.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+// Keeps every selected line with its 1-based line number and offset, so -n, -b and -c see the same selection.
+pub fn search_lines<'a>(config: &Config, contents: &'a str) -> Vec<Match<'a>> {
+    config.searcher().matches(contents).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| String::from(*arg))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     #[test]
-    fn case_sensitive() {
-        let query = "duct";
+    fn inverted_lines_keep_their_line_numbers() {
+        let config = Config::with_settings(
+            args(&["minigrep2", "-v", "-n", "duct", "poem.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
         let contents = "\
 Rust:
 safe, fast, productive.
-Pick three.
-Duct tape.";
+Pick three.";
 
-        assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+        assert!(config.invert_match);
+        assert!(config.line_number);
+        assert_eq!(
+            vec![
+                Match {
+                    line_number: 1,
+                    byte_offset: 0,
+                    line: "Rust:"
+                },
+                Match {
+                    line_number: 3,
+                    byte_offset: 30,
+                    line: "Pick three."
+                }
+            ],
+            search_lines(&config, contents)
+        );
     }
 
     #[test]
-    fn case_insensitive() {
-        let query = "rUsT";
+    fn inverted_lines_ignore_case() {
+        let config = Config::with_settings(
+            args(&["minigrep2", "-i", "-v", "rUsT", "poem.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
         let contents = "\
 Rust:
 safe, fast, productive.
 Pick three.
 Trust me.";
 
+        let lines: Vec<&str> = search_lines(&config, contents)
+            .iter()
+            .map(|found| found.line)
+            .collect();
+        assert_eq!(vec!["safe, fast, productive.", "Pick three."], lines);
+    }
+
+    #[test]
+    fn queries_that_look_like_options() {
+        let after_marker = Config::with_settings(
+            args(&["minigrep2", "-i", "--", "-v", "--help.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
+        assert_eq!("-v", after_marker.query);
+        assert_eq!(vec!["--help.txt"], after_marker.filenames);
+        assert!(!after_marker.invert_match);
+        assert!(!after_marker.case_sensitive);
+
+        let with_e = Config::with_settings(
+            args(&["minigrep2", "-e", "-foo", "-n", "poem.txt", "notes.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
+        assert_eq!("-foo", with_e.query);
+        assert_eq!(vec!["poem.txt", "notes.txt"], with_e.filenames);
+        assert!(with_e.line_number);
+
+        let long = Config::with_settings(
+            args(&["minigrep2", "--pattern=--bogus", "poem.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
+        assert_eq!("--bogus", long.query);
+
+        assert!(Config::with_settings(
+            args(&["minigrep2", "-e", "a", "--query", "b", "poem.txt"]),
+            Settings::new()
+        )
+        .is_err());
+        assert_eq!(
+            Some(String::from("-e needs a value")),
+            Config::with_settings(args(&["minigrep2", "-e"]), Settings::new()).err()
+        );
+    }
+
+    #[test]
+    fn unknown_option_is_rejected() {
+        assert_eq!(
+            Some(String::from(
+                "Unknown option --bogus, to search for it use -e --bogus or put it after --"
+            )),
+            Config::with_settings(
+                args(&["minigrep2", "--bogus", "duct", "poem.txt"]),
+                Settings::new()
+            )
+            .err()
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let mut settings = Settings::new();
+        settings
+            .apply_file(
+                std::path::Path::new("config"),
+                "line_number = true\ncount = true",
+            )
+            .unwrap();
+
+        let config = Config::with_settings(
+            args(&["minigrep2", "--no-count", "duct", "poem.txt"]),
+            settings,
+        )
+        .unwrap();
+
+        assert!(config.line_number);
+        assert!(!config.count);
+        assert_eq!(Source::CommandLine, config.settings.count.source);
+    }
+
+    #[test]
+    fn diacritic_insensitive_offsets_are_in_original_bytes() {
+        let config = Config::with_settings(
+            args(&["minigrep2", "-i", "--ignore-diacritics", "CAFE", "menu.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
+        let contents = "\
+Th\u{e9}
+Caf\u{e9} cr\u{e8}me
+d\u{e9}caf\u{e9}";
+
+        assert_eq!(
+            vec![
+                Match {
+                    line_number: 2,
+                    byte_offset: 5,
+                    line: "Caf\u{e9} cr\u{e8}me"
+                },
+                Match {
+                    line_number: 3,
+                    byte_offset: 21,
+                    line: "d\u{e9}caf\u{e9}"
+                }
+            ],
+            search_lines(&config, contents)
+        );
+    }
+
+    #[test]
+    fn query_option_takes_the_place_of_the_query() {
+        let config = Config::with_settings(
+            args(&[
+                "minigrep2",
+                "--scope=paragraph",
+                "--query",
+                "timeout NOT test",
+                "app.log",
+            ]),
+            Settings::new(),
+        )
+        .unwrap();
+
+        assert_eq!(vec![String::from("app.log")], config.filenames);
+        assert_eq!(Scope::Paragraph, config.scope);
+        assert_eq!(
+            Some(Query::parse("timeout NOT test").unwrap()),
+            config.query_language
+        );
+        assert_eq!(
+            Some(String::from("--query needs a value")),
+            Config::with_settings(args(&["minigrep2", "--query"]), Settings::new()).err()
+        );
+    }
+
+    #[test]
+    fn interactive_takes_no_query_or_output_options() {
+        let options = [
+            "--pattern=duct",
+            "--query=duct",
+            "--rank",
+            "--csv",
+            "--tsv",
+            "--format=sarif",
+        ];
+        for option in &options {
+            assert_eq!(
+                Some(String::from(
+                    "--interactive can't be combined with -e, --query, --rank, --csv, --tsv or --format"
+                )),
+                Config::with_settings(
+                    args(&["minigrep2", "--interactive", option, "poem.txt"]),
+                    Settings::new()
+                )
+                .err(),
+                "{}",
+                option
+            );
+        }
+
+        let config = Config::with_settings(
+            args(&["minigrep2", "--interactive", "poem.txt", "notes.txt"]),
+            Settings::new(),
+        )
+        .unwrap();
+        assert_eq!(vec!["poem.txt", "notes.txt"], config.filenames);
+    }
+
+    #[test]
+    fn field_needs_a_table() {
+        let config = Config::with_settings(
+            args(&["minigrep2", "--csv", "--field=status", "failed", "runs.csv"]),
+            Settings::new(),
+        )
+        .unwrap();
+
+        assert_eq!(Some(Table::Csv), config.table);
+        assert_eq!(Some(String::from("status")), config.field);
         assert_eq!(
-            vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
+            Some(String::from("--field needs --csv or --tsv")),
+            Config::with_settings(
+                args(&["minigrep2", "--field", "2", "failed", "runs.csv"]),
+                Settings::new()
+            )
+            .err()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rank_needs_a_plain_query() {
+        let config = Config::with_settings(
+            args(&["minigrep2", "--rank", "timeout retry", "a.log", "b.log"]),
+            Settings::new(),
+        )
+        .unwrap();
+
+        assert!(config.rank);
+        assert_eq!("timeout retry", config.query);
+        assert!(Config::with_settings(
+            args(&[
+                "minigrep2",
+                "--rank",
+                "--query",
+                "timeout OR retry",
+                "a.log"
+            ]),
+            Settings::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn debug_config_needs_no_query() {
+        let config =
+            Config::with_settings(args(&["minigrep2", "--debug-config"]), Settings::new()).unwrap();
+
+        assert!(config.debug_config);
+        assert_eq!(
+            "\
+ignore_case = false (built-in default)
+invert_match = false (built-in default)
+line_number = false (built-in default)
+count = false (built-in default)
+search_archives = false (built-in default)
+ignore_diacritics = false (built-in default)
+byte_offset = false (built-in default)
+ignore = none (built-in default)
+colour = auto (built-in default)
+context = 0 (built-in default)
+",
+            config.settings.to_string()
+        );
+    }
+
+    #[test]
+    fn ignore_colour_and_context_flags() {
+        let mut settings = Settings::new();
+        settings
+            .apply_file(Path::new("config"), "ignore = target\ncontext = 3")
+            .unwrap();
+        let config = Config::with_settings(
+            args(&[
+                "minigrep2",
+                "--ignore",
+                "*.lock",
+                "--colour=always",
+                "-C",
+                "1",
+                "duct",
+                "src",
+            ]),
+            settings,
+        )
+        .unwrap();
+
+        assert_eq!(vec!["target", "*.lock"], config.ignore);
+        assert_eq!(Colour::Always, config.colour);
+        assert_eq!(1, config.context);
+        assert!(config
+            .settings
+            .to_string()
+            .contains("ignore = target, *.lock (command line)\ncolour = always (command line)\ncontext = 1 (command line)\n"));
+
+        assert_eq!(
+            Some(String::from("Unknown colour sometimes, use auto, always or never")),
+            Config::with_settings(
+                args(&["minigrep2", "--color=sometimes", "duct", "poem.txt"]),
+                Settings::new()
+            )
+            .err()
+        );
+    }
+
+    #[test]
+    fn bad_archives_are_skipped() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let root = std::env::temp_dir().join(format!("minigrep2-archives-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "duct").unwrap();
+        fs::write(root.join("broken.zip"), "not a zip at all").unwrap();
+
+        // a tarball of zeros compresses far beyond MAX_COMPRESSION_RATIO
+        let mut tar = tar::Builder::new(Vec::new());
+        let zeros = vec![0; 1024 * 1024];
+        let mut header = tar::Header::new_gnu();
+        header.set_size(zeros.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "zeros.txt", &zeros[..]).unwrap();
+        let mut gz = GzEncoder::new(Vec::new(), Compression::best());
+        gz.write_all(&tar.into_inner().unwrap()).unwrap();
+        fs::write(root.join("bomb.tar.gz"), gz.finish().unwrap()).unwrap();
+
+        let root_name = root.to_string_lossy().into_owned();
+        let config = Config::with_settings(
+            args(&["minigrep2", "--search-archives", "duct", &root_name]),
+            Settings::new(),
+        )
+        .unwrap();
+        let sources = load_sources(&config);
+        fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<String> = sources.unwrap().into_iter().map(|input| input.name).collect();
+        assert_eq!(vec![root.join("notes.txt").to_string_lossy().into_owned()], names);
+    }
+
+    #[test]
+    fn walked_binary_files_are_skipped() {
+        let root = std::env::temp_dir().join(format!("minigrep2-binary-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "Rust").unwrap();
+        fs::write(root.join("bin.dat"), [0x7f, b'E', b'L', b'F', 0xff, 0xfe, 0x00]).unwrap();
+
+        let root_name = root.to_string_lossy().into_owned();
+        let binary = root.join("bin.dat").to_string_lossy().into_owned();
+        let walked = Config::with_settings(
+            args(&["minigrep2", "-i", "rust", &root_name]),
+            Settings::new(),
+        )
+        .unwrap();
+        let named = Config::with_settings(
+            args(&["minigrep2", "-i", "rust", &binary]),
+            Settings::new(),
+        )
+        .unwrap();
+        let walked = load_sources(&walked);
+        let named = load_sources(&named);
+        fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<String> = walked.unwrap().into_iter().map(|input| input.name).collect();
+        assert_eq!(vec![root.join("notes.txt").to_string_lossy().into_owned()], names);
+        assert_eq!(
+            format!("{}: stream did not contain valid UTF-8, try --encoding", binary),
+            named.err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn walked_directories_skip_ignored_files() {
+        let root = std::env::temp_dir().join(format!("minigrep2-ignore-{}", std::process::id()));
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("notes.txt"), "duct").unwrap();
+        fs::write(root.join("Cargo.lock"), "duct").unwrap();
+        fs::write(root.join("target").join("build.txt"), "duct").unwrap();
+
+        let root_name = root.to_string_lossy().into_owned();
+        let lock = root.join("Cargo.lock").to_string_lossy().into_owned();
+        let ignore = vec![String::from("target"), String::from("*.lock")];
+        let found = expand(&[root_name, lock.clone()], &ignore).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        // a file named on the command line is searched whatever the globs say
+        assert_eq!(
+            vec![root.join("notes.txt").to_string_lossy().into_owned(), lock],
+            found
+        );
+    }
+}
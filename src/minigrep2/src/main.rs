@@ -2,7 +2,7 @@
 
 use std::env;
 use std::process;
-use minigrep2::Config;
+use minigrep2::{Config, Format};
 
 fn main() {
     /**
@@ -21,8 +21,17 @@ fn main() {
         process::exit(1);
     });
 
-    println!("Searching for {}", config.query);
-    println!("In file {}", config.filename);
+    if config.debug_config {
+        print!("{}", config.settings);
+        return;
+    }
+
+    // The picker's choice is the only thing --interactive writes to stdout,
+    // and the other formats are read by programs that don't expect a greeting
+    if !config.interactive && config.format == Format::Plain {
+        println!("Searching for {}", config.query);
+        println!("In file {}", config.filenames.join(", "));
+    }
 
     // We use if let rather than unwrap_or_else to check whether run returns an Err value and call process::exit(1) if it does.
     // Because run returns () in the success case, we only care about detecting an error,
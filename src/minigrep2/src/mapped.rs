@@ -0,0 +1,73 @@
+// Reading big files quickly.
+//
+// Reading a file into a String copies all of it before the search even starts, so
+// larger files are memory-mapped instead: the kernel pages them in as the search walks
+// through, and the only full pass before searching is the UTF-8 check. The searcher in
+// grep-core then skips from hit to hit without splitting the text into lines.
+
+use encoding_rs::Encoding;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+// Below this, reading the file is cheaper than setting up a mapping
+pub const MAP_THRESHOLD: u64 = 64 * 1024;
+
+// The text of an Input, either read into memory or mapped
+pub enum Contents {
+    Owned(String),
+    // checked to be UTF-8 when it was mapped
+    Mapped(Mmap),
+}
+
+impl Contents {
+    // Maps the file. None if it has to be decoded first: it starts with a byte order mark
+    // or isn't UTF-8.
+    pub fn map(file: &File) -> io::Result<Option<Contents>> {
+        // Safety: the mapping is only valid while nobody truncates or rewrites the file.
+        // grep tools generally accept that risk; a file changing mid-search gives odd
+        // results either way.
+        let map = unsafe { Mmap::map(file)? };
+
+        if Encoding::for_bom(&map).is_some() || std::str::from_utf8(&map).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(Contents::Mapped(map)))
+    }
+}
+
+impl Deref for Contents {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Contents::Owned(text) => text,
+            // Safety: Contents::map only builds a Mapped from bytes that passed from_utf8
+            Contents::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+impl From<String> for Contents {
+    fn from(text: String) -> Contents {
+        Contents::Owned(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contents_deref_to_the_text() {
+        let path = std::env::temp_dir().join(format!("minigrep2-mapped-{}", std::process::id()));
+        std::fs::write(&path, "mapped text").unwrap();
+
+        let contents = Contents::map(&File::open(&path).unwrap()).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!("mapped text", &*contents);
+        assert_eq!("read text", &*Contents::from(String::from("read text")));
+    }
+}
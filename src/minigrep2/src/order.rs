@@ -0,0 +1,177 @@
+// --sort and --unique: output in an order that doesn't change between runs.
+//
+// Files found by walking a directory come in whatever order the filesystem keeps them,
+// which differs between machines and even between runs. --sort puts the searched files
+// in a fixed order first:
+//
+// path   by name, compared byte by byte
+// mtime  least recently modified first (archive entries use the archive's time)
+// count  most matching lines first
+//
+// and anything still equal is ordered by path, so the output is the same every time.
+//
+// --unique prints every distinct matching line once, after how often it was found across
+// all the files, like `sort | uniq -c`. Lines come in the order they were first found,
+// or with --sort=count most frequent first.
+
+use crate::{search_lines, Config, Input};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Path,
+    Mtime,
+    Count,
+}
+
+impl Sort {
+    pub fn parse(name: &str) -> Result<Sort, String> {
+        match name {
+            "path" => Ok(Sort::Path),
+            "mtime" => Ok(Sort::Mtime),
+            "count" => Ok(Sort::Count),
+            _ => Err(format!("Unknown sort {}, use path, mtime or count", name)),
+        }
+    }
+}
+
+// The file an input was read from; for archive.zip!inner.txt that is the archive
+fn file_of(input: &Input) -> &str {
+    match input.name.find('!') {
+        Some(at) => &input.name[..at],
+        None => &input.name,
+    }
+}
+
+fn modified(input: &Input) -> Option<SystemTime> {
+    fs::metadata(file_of(input))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub fn sort_sources(config: &Config, sort: Sort, sources: &mut [Input]) {
+    match sort {
+        Sort::Path => sources.sort_by(|a, b| a.name.cmp(&b.name)),
+        Sort::Mtime => sources.sort_by_cached_key(|input| (modified(input), input.name.clone())),
+        Sort::Count => sources.sort_by_cached_key(|input| {
+            let count = search_lines(config, &input.contents).len();
+            (std::cmp::Reverse(count), input.name.clone())
+        }),
+    }
+}
+
+// The same orders for files --bytes reads itself rather than as Inputs;
+// count says how many matches a file has
+pub fn sort_paths<F: Fn(&str) -> usize>(sort: Sort, paths: &mut [String], count: F) {
+    match sort {
+        Sort::Path => paths.sort(),
+        Sort::Mtime => paths.sort_by_cached_key(|path| {
+            let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+            (modified.ok(), path.clone())
+        }),
+        Sort::Count => {
+            paths.sort_by_cached_key(|path| (std::cmp::Reverse(count(path)), path.clone()))
+        }
+    }
+}
+
+// Every distinct selected line with how many times it was found, in the order --unique prints them
+pub fn unique_lines<'a>(config: &Config, sources: &'a [Input]) -> Vec<(&'a str, usize)> {
+    let mut lines: Vec<(&str, usize)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for input in sources {
+        for found in search_lines(config, &input.contents) {
+            match index.get(found.line) {
+                Some(&at) => lines[at].1 += 1,
+                None => {
+                    index.insert(found.line, lines.len());
+                    lines.push((found.line, 1));
+                }
+            }
+        }
+    }
+
+    if config.sort == Some(Sort::Count) {
+        lines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    }
+
+    lines
+}
+
+pub fn write_unique<W: Write>(config: &Config, sources: &[Input], out: &mut W) -> io::Result<()> {
+    for (line, count) in unique_lines(config, sources) {
+        writeln!(out, "{:>7} {}", count, line)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Settings;
+
+    fn config(args: &[&str]) -> Config {
+        let args = args.iter().map(|arg| arg.to_string());
+        Config::with_settings(args, Settings::new()).unwrap()
+    }
+
+    fn sources() -> Vec<Input> {
+        vec![
+            Input::new(String::from("b.log"), String::from("error: disk full\nok")),
+            Input::new(
+                String::from("c.log"),
+                String::from("error: timeout\nerror: disk full\nerror: timeout"),
+            ),
+            Input::new(String::from("a.log"), String::from("error: timeout")),
+        ]
+    }
+
+    fn names(sources: &[Input]) -> Vec<&str> {
+        sources.iter().map(|input| input.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_by_path() {
+        let config = config(&["minigrep2", "--sort=path", "error", "x"]);
+        let mut sources = sources();
+
+        sort_sources(&config, Sort::Path, &mut sources);
+
+        assert_eq!(vec!["a.log", "b.log", "c.log"], names(&sources));
+    }
+
+    #[test]
+    fn sort_by_count_breaks_ties_by_path() {
+        let config = config(&["minigrep2", "--sort=count", "error", "x"]);
+        let mut sources = sources();
+
+        sort_sources(&config, Sort::Count, &mut sources);
+
+        assert_eq!(vec!["c.log", "a.log", "b.log"], names(&sources));
+    }
+
+    #[test]
+    fn unique_counts_lines_across_files() {
+        let in_order = config(&["minigrep2", "--unique", "error", "x"]);
+        assert_eq!(
+            vec![("error: disk full", 2), ("error: timeout", 3)],
+            unique_lines(&in_order, &sources())
+        );
+
+        let by_count = config(&["minigrep2", "--unique", "--sort=count", "error", "x"]);
+        assert_eq!(
+            vec![("error: timeout", 3), ("error: disk full", 2)],
+            unique_lines(&by_count, &sources())
+        );
+    }
+
+    #[test]
+    fn unknown_sort() {
+        assert!(Sort::parse("size").is_err());
+    }
+}
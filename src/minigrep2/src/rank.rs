@@ -0,0 +1,214 @@
+// --rank: best matching files first.
+//
+// The query is split into terms ("timeout retry backoff") and every file gets an Okapi BM25 score:
+//
+//   score(file) = sum over terms t of  idf(t) * tf * (K1 + 1) / (tf + K1 * (1 - B + B * len / avg_len))
+//
+// tf       how often t occurs in the file
+// idf(t)   ln((N - n + 0.5) / (n + 0.5) + 1), where N is the number of files searched and
+//          n the number of them containing t, so a term found everywhere is worth little
+// len      the file's length in words; dividing by the average keeps long files from
+//          winning just because they have more words
+//
+// Files are printed from the highest score down, each with its best lines.
+
+use crate::{Folding, Input};
+use std::io::{self, Write};
+
+// The usual BM25 constants: K1 limits how much repeating a term helps, B how much length counts.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+// Lines shown under each file
+pub const TOP_LINES: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub struct Ranked<'a> {
+    // index into the searched inputs
+    pub source: usize,
+    pub score: f64,
+    // (line number, line), best first
+    pub top_lines: Vec<(usize, &'a str)>,
+}
+
+fn words(folding: Folding, text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| folding.fold(word))
+        .collect()
+}
+
+pub fn rank<'a>(query: &str, folding: Folding, inputs: &'a [Input]) -> Vec<Ranked<'a>> {
+    let terms = words(folding, query);
+    let documents: Vec<Vec<String>> = inputs
+        .iter()
+        .map(|input| words(folding, &input.contents))
+        .collect();
+
+    let count = documents.len() as f64;
+    let average_length =
+        documents.iter().map(|words| words.len()).sum::<usize>() as f64 / count.max(1.0);
+
+    let idf: Vec<f64> = terms
+        .iter()
+        .map(|term| {
+            let containing = documents
+                .iter()
+                .filter(|words| words.contains(term))
+                .count() as f64;
+            ((count - containing + 0.5) / (containing + 0.5) + 1.0).ln()
+        })
+        .collect();
+
+    let mut ranked: Vec<Ranked> = documents
+        .iter()
+        .enumerate()
+        .map(|(source, words)| {
+            let length = words.len() as f64;
+            let score = terms
+                .iter()
+                .zip(&idf)
+                .map(|(term, idf)| {
+                    let tf = words.iter().filter(|word| *word == term).count() as f64;
+                    idf * tf * (K1 + 1.0)
+                        / (tf + K1 * (1.0 - B + B * length / average_length.max(1.0)))
+                })
+                .sum();
+
+            Ranked {
+                source,
+                score,
+                top_lines: top_lines(&terms, folding, &inputs[source].contents),
+            }
+        })
+        .filter(|ranked| ranked.score > 0.0)
+        .collect();
+
+    // Equal scores keep the order the files were given in, so the output is stable
+    ranked.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    ranked
+}
+
+// The lines with the most query terms in them; among equals, the earlier line
+fn top_lines<'a>(terms: &[String], folding: Folding, contents: &'a str) -> Vec<(usize, &'a str)> {
+    let mut lines: Vec<(usize, usize, &str)> = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let hits = words(folding, line)
+                .iter()
+                .filter(|word| terms.contains(word))
+                .count();
+            (hits, index + 1, line)
+        })
+        .filter(|(hits, _, _)| *hits > 0)
+        .collect();
+
+    lines.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    lines
+        .into_iter()
+        .take(TOP_LINES)
+        .map(|(_, number, line)| (number, line))
+        .collect()
+}
+
+pub fn write_ranked<W: Write>(
+    query: &str,
+    folding: Folding,
+    inputs: &[Input],
+    out: &mut W,
+) -> io::Result<()> {
+    for ranked in rank(query, folding, inputs) {
+        writeln!(out, "{:.3} {}", ranked.score, inputs[ranked.source].name)?;
+        for (number, line) in ranked.top_lines {
+            writeln!(out, "    {}:{}", number, line)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOSE: Folding = Folding {
+        ignore_case: true,
+        ignore_diacritics: false,
+    };
+
+    fn inputs(files: &[(&str, &str)]) -> Vec<Input> {
+        files
+            .iter()
+            .map(|(name, contents)| Input::new(name.to_string(), contents.to_string()))
+            .collect()
+    }
+
+    fn order(ranked: &[Ranked], inputs: &[Input]) -> Vec<String> {
+        ranked
+            .iter()
+            .map(|ranked| inputs[ranked.source].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn more_occurrences_rank_higher() {
+        let inputs = inputs(&[
+            ("once.log", "timeout after 5s\nall good\nall good"),
+            ("twice.log", "timeout after 5s\ntimeout again\nall good"),
+            ("none.log", "all good\nall good\nall good"),
+        ]);
+
+        let ranked = rank("Timeout", LOOSE, &inputs);
+
+        assert_eq!(vec!["twice.log", "once.log"], order(&ranked, &inputs));
+    }
+
+    #[test]
+    fn rare_terms_weigh_more() {
+        // "retry" is in every file, "backoff" only in one
+        let inputs = inputs(&[
+            ("a.log", "retry retry retry"),
+            ("b.log", "retry backoff"),
+            ("c.log", "retry retry"),
+        ]);
+
+        let ranked = rank("retry backoff", LOOSE, &inputs);
+
+        assert_eq!("b.log", inputs[ranked[0].source].name);
+    }
+
+    #[test]
+    fn shorter_files_win_ties_in_frequency() {
+        let inputs = inputs(&[
+            (
+                "long.log",
+                "timeout and a lot of other words that dilute the match",
+            ),
+            ("short.log", "timeout here"),
+            ("other.log", "nothing"),
+        ]);
+
+        let ranked = rank("timeout", LOOSE, &inputs);
+
+        assert_eq!(vec!["short.log", "long.log"], order(&ranked, &inputs));
+    }
+
+    #[test]
+    fn top_lines_have_the_most_terms() {
+        let inputs = inputs(&[
+            (
+                "app.log",
+                "start\ntimeout\nretry after timeout\nok\nretry\nretry\nretry",
+            ),
+            ("other.log", "nothing"),
+        ]);
+
+        let ranked = rank("timeout retry", LOOSE, &inputs);
+
+        assert_eq!(
+            vec![(3, "retry after timeout"), (2, "timeout"), (5, "retry")],
+            ranked[0].top_lines
+        );
+    }
+}
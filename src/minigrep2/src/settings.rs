@@ -0,0 +1,372 @@
+// Default options for minigrep2, merged from several places.
+//
+// Each setting starts at a built-in default, can be changed by the config file
+// ($MINIGREP_CONFIG or ~/.config/minigrep/config), then by the CASE_INSENSITIVE
+// environment variable, and finally by a command-line flag. Every value remembers
+// where it came from, so --debug-config can explain the result.
+//
+// Besides the true/false switches the file can hold
+//
+//     ignore = target, *.min.js    # globs a directory search skips, see ignore.rs
+//     colour = auto                # highlight matches: auto (on a terminal), always or never
+//     context = 2                  # lines to print around each match, like grep -C
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    ConfigFile(PathBuf),
+    Environment(&'static str),
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "built-in default"),
+            Source::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            Source::Environment(name) => write!(f, "environment variable {}", name),
+            Source::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Colour {
+    Auto,
+    Always,
+    Never,
+}
+
+impl Colour {
+    pub fn parse(name: &str) -> Result<Colour, String> {
+        match name {
+            "auto" => Ok(Colour::Auto),
+            "always" => Ok(Colour::Always),
+            "never" => Ok(Colour::Never),
+            _ => Err(format!(
+                "Unknown colour {}, use auto, always or never",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Colour::Auto => "auto",
+            Colour::Always => "always",
+            Colour::Never => "never",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn default(value: T) -> Setting<T> {
+        Setting {
+            value,
+            source: Source::Default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub ignore_case: Setting<bool>,
+    pub invert_match: Setting<bool>,
+    pub line_number: Setting<bool>,
+    pub count: Setting<bool>,
+    pub search_archives: Setting<bool>,
+    pub ignore_diacritics: Setting<bool>,
+    pub byte_offset: Setting<bool>,
+    pub ignore: Setting<Vec<String>>,
+    pub colour: Setting<Colour>,
+    pub context: Setting<usize>,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            ignore_case: Setting::default(false),
+            invert_match: Setting::default(false),
+            line_number: Setting::default(false),
+            count: Setting::default(false),
+            search_archives: Setting::default(false),
+            ignore_diacritics: Setting::default(false),
+            byte_offset: Setting::default(false),
+            ignore: Setting::default(Vec::new()),
+            colour: Setting::default(Colour::Auto),
+            context: Setting::default(0),
+        }
+    }
+
+    // Built-in defaults, then the config file, then the environment.
+    // Command-line flags are applied on top of this by Config::new.
+    pub fn load() -> Result<Settings, String> {
+        let mut settings = Settings::new();
+
+        if let Some((path, required)) = config_path() {
+            match fs::read_to_string(&path) {
+                Ok(contents) => settings.apply_file(&path, &contents)?,
+                // The default location is optional, but a file named by $MINIGREP_CONFIG must exist.
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound && !required => {}
+                Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+            }
+        }
+
+        if env::var("CASE_INSENSITIVE").is_ok() {
+            settings.set("ignore_case", true, Source::Environment("CASE_INSENSITIVE"))?;
+        }
+
+        Ok(settings)
+    }
+
+    // The config file holds one `key = value` pair per line. Blank lines and lines starting with # are ignored.
+    pub fn apply_file(&mut self, path: &Path, contents: &str) -> Result<(), String> {
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(at) => (line[..at].trim(), line[at + 1..].trim()),
+                None => {
+                    return Err(format!(
+                        "{}:{}: expected `key = value`",
+                        path.display(),
+                        index + 1
+                    ))
+                }
+            };
+
+            self.set_value(key, value, Source::ConfigFile(path.to_path_buf()))
+                .map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: bool, source: Source) -> Result<(), String> {
+        let setting = self
+            .switch(key)
+            .ok_or_else(|| format!("unknown setting `{}`", key))?;
+
+        *setting = Setting { value, source };
+        Ok(())
+    }
+
+    // Any setting from its text, as the config file and --colour or --context give it.
+    // A list of globs is separated by commas.
+    pub fn set_value(&mut self, key: &str, value: &str, source: Source) -> Result<(), String> {
+        match key {
+            "ignore" => {
+                let globs = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|glob| !glob.is_empty())
+                    .map(String::from)
+                    .collect();
+                self.ignore = Setting {
+                    value: globs,
+                    source,
+                };
+            }
+            "colour" => {
+                self.colour = Setting {
+                    value: Colour::parse(value)?,
+                    source,
+                };
+            }
+            "context" => {
+                let lines = value
+                    .parse()
+                    .map_err(|_| String::from("`context` must be a number of lines"))?;
+                self.context = Setting {
+                    value: lines,
+                    source,
+                };
+            }
+            _ if self.switch(key).is_none() => return Err(format!("unknown setting `{}`", key)),
+            _ => match value {
+                "true" => self.set(key, true, source)?,
+                "false" => self.set(key, false, source)?,
+                _ => return Err(format!("`{}` must be true or false", key)),
+            },
+        }
+
+        Ok(())
+    }
+
+    // --ignore adds to the globs the config file gave, rather than replacing them
+    pub fn add_ignore(&mut self, glob: String, source: Source) {
+        self.ignore.value.push(glob);
+        self.ignore.source = source;
+    }
+
+    // The true/false settings by name
+    fn switch(&mut self, key: &str) -> Option<&mut Setting<bool>> {
+        match key {
+            "ignore_case" => Some(&mut self.ignore_case),
+            "invert_match" => Some(&mut self.invert_match),
+            "line_number" => Some(&mut self.line_number),
+            "count" => Some(&mut self.count),
+            "search_archives" => Some(&mut self.search_archives),
+            "ignore_diacritics" => Some(&mut self.ignore_diacritics),
+            "byte_offset" => Some(&mut self.byte_offset),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+// --debug-config prints one line per setting, e.g. `line_number = true (config file /home/me/.config/minigrep/config)`
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = [
+            ("ignore_case", &self.ignore_case),
+            ("invert_match", &self.invert_match),
+            ("line_number", &self.line_number),
+            ("count", &self.count),
+            ("search_archives", &self.search_archives),
+            ("ignore_diacritics", &self.ignore_diacritics),
+            ("byte_offset", &self.byte_offset),
+        ];
+
+        for (key, setting) in rows.iter() {
+            writeln!(f, "{} = {} ({})", key, setting.value, setting.source)?;
+        }
+
+        // written the way the config file takes them
+        let ignore = if self.ignore.value.is_empty() {
+            String::from("none")
+        } else {
+            self.ignore.value.join(", ")
+        };
+        writeln!(f, "ignore = {} ({})", ignore, self.ignore.source)?;
+        writeln!(f, "colour = {} ({})", self.colour.value, self.colour.source)?;
+        writeln!(
+            f,
+            "context = {} ({})",
+            self.context.value, self.context.source
+        )
+    }
+}
+
+// Returns the config file to read and whether it has to exist.
+fn config_path() -> Option<(PathBuf, bool)> {
+    if let Some(path) = env::var_os("MINIGREP_CONFIG") {
+        return Some((PathBuf::from(path), true));
+    }
+
+    env::var_os("HOME").map(|home| {
+        let path = PathBuf::from(home)
+            .join(".config")
+            .join("minigrep")
+            .join("config");
+        (path, false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file_overrides_defaults() {
+        let path = Path::new("config");
+        let contents = "\
+# defaults for minigrep2
+line_number = true
+
+count = false";
+
+        let mut settings = Settings::new();
+        settings.apply_file(path, contents).unwrap();
+
+        assert!(settings.line_number.value);
+        assert_eq!(
+            Source::ConfigFile(PathBuf::from("config")),
+            settings.line_number.source
+        );
+        assert_eq!(
+            Source::ConfigFile(PathBuf::from("config")),
+            settings.count.source
+        );
+        assert_eq!(Source::Default, settings.invert_match.source);
+    }
+
+    #[test]
+    fn config_file_errors_name_the_line() {
+        let path = Path::new("config");
+
+        let mut settings = Settings::new();
+        assert_eq!(
+            Ok(()),
+            settings.apply_file(path, "count = true\ncolour = never")
+        );
+        assert_eq!(Colour::Never, settings.colour.value);
+        assert_eq!(
+            Err(String::from("config:2: unknown setting `pager`")),
+            settings.apply_file(path, "count = true\npager = true")
+        );
+        assert_eq!(
+            Err(String::from("config:1: `count` must be true or false")),
+            settings.apply_file(path, "count = yes")
+        );
+        assert_eq!(
+            Err(String::from(
+                "config:1: Unknown colour true, use auto, always or never"
+            )),
+            settings.apply_file(path, "colour = true")
+        );
+        assert_eq!(
+            Err(String::from(
+                "config:3: `context` must be a number of lines"
+            )),
+            settings.apply_file(path, "colour = never\n\ncontext = -1")
+        );
+    }
+
+    #[test]
+    fn config_file_holds_ignore_colour_and_context() {
+        let path = Path::new("config");
+        let contents = "\
+ignore = target, *.min.js
+colour = always
+context = 2";
+
+        let mut settings = Settings::new();
+        settings.apply_file(path, contents).unwrap();
+
+        assert_eq!(vec!["target", "*.min.js"], settings.ignore.value);
+        assert_eq!(Colour::Always, settings.colour.value);
+        assert_eq!(2, settings.context.value);
+        assert_eq!(
+            Source::ConfigFile(PathBuf::from("config")),
+            settings.colour.source
+        );
+
+        settings.add_ignore(String::from("*.lock"), Source::CommandLine);
+        assert_eq!(vec!["target", "*.min.js", "*.lock"], settings.ignore.value);
+        assert_eq!(Source::CommandLine, settings.ignore.source);
+    }
+}
@@ -0,0 +1,242 @@
+// --csv / --tsv: search records instead of lines.
+//
+// A CSV record can span several lines when a quoted field contains a newline, and a line
+// search would match a query in any column, so in these modes the file is parsed into
+// records first (the first record is the header). The query is checked against every field,
+// or with --field only against one: `--field status` by header name, `--field 3` by
+// 1-based position. A selected record is printed whole, one `header=value` per field:
+//
+//     id=7, status=failed, note="retried\ntwice"
+//
+// Values containing the separator, a quote or a line break are quoted and escaped, so
+// every record stays on one output line. -n prints the line the record starts on, -b
+// its offset and -c the number of selected records.
+
+use crate::{Config, Input, Searcher};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table {
+    Csv,
+    Tsv,
+}
+
+impl Table {
+    fn separator(self) -> u8 {
+        match self {
+            Table::Csv => b',',
+            Table::Tsv => b'\t',
+        }
+    }
+}
+
+pub struct Record {
+    // 1-based line the record starts on
+    pub line_number: usize,
+    pub byte_offset: usize,
+    pub fields: Vec<String>,
+}
+
+// The header and every record after it
+pub fn parse(table: Table, contents: &str) -> Result<(Vec<String>, Vec<Record>), csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(table.separator())
+        // a record with a missing or extra field is still worth searching
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let header = reader.headers()?.iter().map(String::from).collect();
+
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let position = record
+            .position()
+            .expect("records read from a file have a position");
+        records.push(Record {
+            line_number: position.line() as usize,
+            byte_offset: position.byte() as usize,
+            fields: record.iter().map(String::from).collect(),
+        });
+    }
+
+    Ok((header, records))
+}
+
+// Which column --field means in this file. A header name wins over a position,
+// so a column that is really called "3" can still be picked.
+pub fn column(field: &str, header: &[String]) -> Result<usize, String> {
+    if let Some(index) = header.iter().position(|name| name == field) {
+        return Ok(index);
+    }
+
+    match field.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= header.len() => Ok(number - 1),
+        Ok(number) => Err(format!(
+            "There is no field {}, the header has {}",
+            number,
+            header.len()
+        )),
+        Err(_) => Err(format!("There is no field named {}", field)),
+    }
+}
+
+fn selected(config: &Config, searcher: &Searcher, record: &Record, column: Option<usize>) -> bool {
+    let is_match = |field: &String| searcher.contains(field);
+
+    let found = match column {
+        Some(column) => record.fields.get(column).is_some_and(is_match),
+        None => record.fields.iter().any(is_match),
+    };
+    found != config.invert_match
+}
+
+pub fn format_record(table: Table, header: &[String], fields: &[String]) -> String {
+    let separator = table.separator() as char;
+
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            // A record can have more fields than the header; those are named by position
+            let name = match header.get(index) {
+                Some(name) => name.clone(),
+                None => (index + 1).to_string(),
+            };
+            if value.contains([separator, '"', '\n', '\r']) {
+                format!("{}={:?}", name, value)
+            } else {
+                format!("{}={}", name, value)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn write_records<W: Write>(
+    config: &Config,
+    table: Table,
+    sources: &[Input],
+    out: &mut W,
+) -> io::Result<()> {
+    let show_names = sources.len() > 1 || sources.iter().any(|input| input.name.contains('!'));
+    let searcher = config.searcher();
+
+    for input in sources {
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", input.name, message),
+            )
+        };
+
+        let (header, records) =
+            parse(table, &input.contents).map_err(|e| invalid(e.to_string()))?;
+        let column = match &config.field {
+            Some(field) => Some(column(field, &header).map_err(invalid)?),
+            None => None,
+        };
+
+        let prefix = if show_names {
+            format!("{}:", input.name)
+        } else {
+            String::new()
+        };
+
+        let records: Vec<&Record> = records
+            .iter()
+            .filter(|record| selected(config, &searcher, record, column))
+            .collect();
+
+        if config.count {
+            writeln!(out, "{}{}", prefix, records.len())?;
+            continue;
+        }
+
+        for record in records {
+            let mut prefix = prefix.clone();
+            if config.line_number {
+                prefix.push_str(&format!("{}:", record.line_number));
+            }
+            if config.byte_offset {
+                prefix.push_str(&format!("{}:", input.original_offset(record.byte_offset)));
+            }
+            writeln!(
+                out,
+                "{}{}",
+                prefix,
+                format_record(table, &header, &record.fields)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNS: &str = "\
+id,status,note
+1,ok,fine
+2,failed,\"retried
+then gave up, \"\"timeout\"\"\"
+3,ok,failed once
+";
+
+    fn header() -> Vec<String> {
+        vec![
+            String::from("id"),
+            String::from("status"),
+            String::from("note"),
+        ]
+    }
+
+    #[test]
+    fn quoted_newlines_stay_in_one_record() {
+        let (header, records) = parse(Table::Csv, RUNS).unwrap();
+
+        assert_eq!(vec!["id", "status", "note"], header);
+        assert_eq!(3, records.len());
+        assert_eq!(
+            vec!["2", "failed", "retried\nthen gave up, \"timeout\""],
+            records[1].fields
+        );
+        assert_eq!(3, records[1].line_number);
+        assert_eq!(5, records[2].line_number);
+    }
+
+    #[test]
+    fn tsv_splits_on_tabs() {
+        let (header, records) = parse(Table::Tsv, "name\tcity\nAnn\tOslo, Norway\n").unwrap();
+
+        assert_eq!(vec!["name", "city"], header);
+        assert_eq!(vec!["Ann", "Oslo, Norway"], records[0].fields);
+    }
+
+    #[test]
+    fn field_by_name_or_position() {
+        let header = header();
+
+        assert_eq!(Ok(1), column("status", &header));
+        assert_eq!(Ok(2), column("3", &header));
+        assert!(column("4", &header).is_err());
+        assert!(column("0", &header).is_err());
+        assert!(column("owner", &header).is_err());
+    }
+
+    #[test]
+    fn records_print_with_header_names() {
+        let fields = vec![
+            String::from("2"),
+            String::from("failed"),
+            String::from("retried\nthen gave up, \"timeout\""),
+        ];
+
+        assert_eq!(
+            "id=2, status=failed, note=\"retried\\nthen gave up, \\\"timeout\\\"\"",
+            format_record(Table::Csv, &header(), &fields)
+        );
+    }
+}
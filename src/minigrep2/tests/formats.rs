@@ -0,0 +1,119 @@
+// Golden-file tests for --format.
+//
+// Each format is run over tests/golden/poem.txt and compared with tests/golden/poem.<format>.
+// After an intended change to a format, regenerate the files with
+//     MINIGREP_UPDATE_GOLDEN=1 cargo test --test formats
+// and review the diff.
+use minigrep2::{write_results, Config, Input, Settings};
+use std::env;
+use std::fs;
+
+fn check_golden(format: &str) {
+    let args = vec![
+        "minigrep2",
+        "-i",
+        "--format",
+        format,
+        "rust",
+        "tests/golden/poem.txt",
+    ];
+    let config =
+        Config::with_settings(args.into_iter().map(String::from), Settings::new()).unwrap();
+    let inputs = vec![Input::new(
+        String::from("tests/golden/poem.txt"),
+        fs::read_to_string("tests/golden/poem.txt").unwrap(),
+    )];
+
+    let mut output = Vec::new();
+    write_results(&config, &inputs, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let golden = format!("tests/golden/poem.{}", format);
+    if env::var("MINIGREP_UPDATE_GOLDEN").is_ok() {
+        fs::write(&golden, &output).unwrap();
+    }
+
+    assert_eq!(
+        fs::read_to_string(&golden).unwrap(),
+        output,
+        "{} differs",
+        golden
+    );
+}
+
+#[test]
+fn vimgrep() {
+    check_golden("vimgrep");
+}
+
+#[test]
+fn quickfix() {
+    check_golden("quickfix");
+}
+
+#[test]
+fn sarif() {
+    check_golden("sarif");
+}
+
+#[test]
+fn sarif_is_valid_json() {
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string("tests/golden/poem.sarif").unwrap()).unwrap();
+
+    assert_eq!("2.1.0", report["version"]);
+    assert_eq!(4, report["runs"][0]["results"].as_array().unwrap().len());
+}
+
+// Plain output, the format people read, checked directly rather than against a golden file
+fn plain(args: &[&str], contents: &str) -> String {
+    let args = ["minigrep2"].iter().chain(args).chain(["notes.txt"].iter());
+    let config =
+        Config::with_settings(args.map(|arg| String::from(*arg)), Settings::new()).unwrap();
+    let inputs = vec![Input::new(
+        String::from("notes.txt"),
+        String::from(contents),
+    )];
+
+    let mut output = Vec::new();
+    write_results(&config, &inputs, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn context_lines_surround_the_matches() {
+    let contents = "a\nmatch one\nb\nc\nd\ne\nmatch two\nf\n";
+
+    assert_eq!(
+        "1-a\n2:match one\n3-b\n--\n6-e\n7:match two\n8-f\n",
+        plain(&["-n", "-C", "1", "match"], contents)
+    );
+    // groups that touch are printed as one
+    assert_eq!(
+        "a\nmatch one\nb\nc\nd\ne\nmatch two\nf\n",
+        plain(&["--context=2", "match"], contents)
+    );
+    assert_eq!(
+        "0-a\n8:match one\n12-b\n",
+        plain(&["-b", "-C", "1", "one"], contents)
+    );
+}
+
+#[test]
+fn colour_highlights_the_matches() {
+    let contents = fs::read_to_string("tests/golden/poem.txt").unwrap();
+
+    assert_eq!(
+        "T\x1b[1;31mrust\x1b[0m me, \x1b[1;31mrust\x1b[0m is \x1b[1;31mrust\x1b[0my.\n",
+        plain(&["--colour=always", "rust"], &contents)
+    );
+    assert_eq!(
+        "Trust me, rust is rusty.\n",
+        plain(&["--colour=never", "rust"], &contents)
+    );
+    // auto is decided by run, against the real standard output
+    assert_eq!(
+        "Trust me, rust is rusty.\n",
+        plain(&["--color", "auto", "rust"], &contents)
+    );
+}